@@ -1,13 +1,15 @@
 use crate::init::ConsoleMessage;
 use ::syscall::print_error;
 use alloc::vec::Vec;
-use core::mem;
 use core::ops::{Coroutine, CoroutineState};
 use core::pin::Pin;
-use klib::ipc::{self, MessageType, Notifications};
+use klib::ipc::{self, MessageType, NotificationPayload};
 use klib::result::KResult;
 use syscall::syscall;
 
+// Each running generator sleeps on the timer of the same index.
+const NUM_GENERATORS: usize = 4;
+
 #[repr(u32)]
 enum GeneratorCommand {
     Continue,
@@ -31,19 +33,15 @@ fn delayed_writer() -> impl Coroutine<GeneratorResponse, Yield = GeneratorComman
         let src_text = ConsoleMessage::text_of(&message);
         syscall::console_write(src_text);
         let text: Vec<u8> = src_text.iter().cloned().collect();
-        print_error!(b"text: {}\n", unsafe {
-            mem::transmute::<*const u8, u32>(text.as_ptr())
-        });
+        print_error!(b"text: {}\n", text.as_ptr() as u32);
         let text2: Vec<u8> = src_text.iter().cloned().collect();
-        print_error!(b"text2: {}\n", unsafe {
-            mem::transmute::<*const u8, u32>(text2.as_ptr())
-        });
+        print_error!(b"text2: {}\n", text2.as_ptr() as u32);
         yield GeneratorCommand::Sleep(100);
         syscall::console_write(&text);
     }
 }
 
-fn run_generator<G>(generator: &mut Option<G>, mut response: GeneratorResponse)
+fn run_generator<G>(generator: &mut Option<G>, timer_id: u32, mut response: GeneratorResponse)
 where
     G: Coroutine<GeneratorResponse, Yield = GeneratorCommand> + core::marker::Unpin,
 {
@@ -64,9 +62,12 @@ where
                     }
                 }
             }
-            GeneratorCommand::Complete => break,
+            GeneratorCommand::Complete => {
+                *generator = None;
+                break;
+            }
             GeneratorCommand::Sleep(sleep_ms) => {
-                syscall::set_timer(sleep_ms);
+                syscall::timer_set(timer_id, sleep_ms, 0);
                 break;
             }
         }
@@ -76,20 +77,33 @@ where
 #[no_mangle]
 pub extern "C" fn console_task() {
     syscall::console_write(b"generator console task started\n");
-    let mut generator = None;
+    // The element type is inferred from `delayed_writer` below.
+    let mut generators: [Option<_>; NUM_GENERATORS] = [None, None, None, None];
+    let mut text = [0; ConsoleMessage::MAX_TEXT_LEN];
 
     loop {
-        match syscall::ipc_recv(0) {
+        match syscall::ipc_recv_ool(0, &mut text) {
             KResult::Ok(message) => match message.message_type {
                 ConsoleMessage::CONSOLE_OUT => {
-                    generator = Some(delayed_writer());
-                    run_generator(&mut generator, GeneratorResponse::Message(message));
+                    if let Some(i) = generators.iter().position(|g| g.is_none()) {
+                        generators[i] = Some(delayed_writer());
+                        run_generator(
+                            &mut generators[i],
+                            i as u32,
+                            GeneratorResponse::Message(message),
+                        );
+                    } else {
+                        syscall::console_write(b"too many pending console messages\n");
+                    }
                 }
                 MessageType::NOTIFICATIONS => {
-                    let notifications =
-                        unsafe { *<*const _>::from(&message.raw).cast::<Notifications>() };
-                    if notifications.is_timer() {
-                        run_generator(&mut generator, GeneratorResponse::None);
+                    let payload = NotificationPayload::parse(&message);
+                    if let Some(payload) = payload.filter(|p| p.notifications.is_timer()) {
+                        for (i, generator) in generators.iter_mut().enumerate() {
+                            if payload.fired_timers & (1 << i) != 0 {
+                                run_generator(generator, i as u32, GeneratorResponse::None);
+                            }
+                        }
                     }
                 }
                 _ => (),
@@ -1,3 +1,5 @@
+#[cfg(feature = "ipc-bench")]
+use crate::ipc_bench::{IPC_BENCH_SERVER_TID, IPC_BENCH_TASK_TID};
 use ::syscall::print_error;
 use alloc::alloc;
 use core::alloc::{GlobalAlloc, Layout};
@@ -9,15 +11,19 @@ use klib::cycle;
 use klib::ipc::{Message, MessageType};
 use klib::local_address_of;
 use klib::result::KResult;
+use klib::task::TASK_PRIORITY_MAX;
 use syscall::syscall;
 
+const SERVER_TASK_PRIORITY: u32 = TASK_PRIORITY_MAX - 2;
+
+#[cfg_attr(any(test, feature = "hosted"), allow(dead_code))]
 struct HeapAllocator;
 
 unsafe impl GlobalAlloc for HeapAllocator {
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
         let result = syscall::ipc_call(
             tid::MALLOC_TASK_TID,
-            &AllocMessage::request(layout.size(), layout.align()),
+            &mut AllocMessage::request(layout.size(), layout.align()),
         );
         match result {
             KResult::Ok(response) => AllocMessage::parse_response(&response),
@@ -29,7 +35,7 @@ unsafe impl GlobalAlloc for HeapAllocator {
     }
 
     unsafe fn dealloc(&self, ptr: *mut u8, _layout: Layout) {
-        let result = syscall::ipc_call(tid::MALLOC_TASK_TID, &DeallocMessage::request(ptr));
+        let result = syscall::ipc_call(tid::MALLOC_TASK_TID, &mut DeallocMessage::request(ptr));
         match result {
             KResult::Ok(_) => (),
             err => {
@@ -39,74 +45,93 @@ unsafe impl GlobalAlloc for HeapAllocator {
     }
 }
 
+// On the host, the tasks allocate from the heap of the host instead.
+#[cfg(not(any(test, feature = "hosted")))]
 #[global_allocator]
 static ALLOCATOR: HeapAllocator = HeapAllocator {};
 
+// Allocates a stack for `syscall::create_task_with_stack`.
+fn alloc_stack(size: usize) -> &'static mut [u8] {
+    unsafe {
+        slice::from_raw_parts_mut(
+            alloc::alloc(Layout::from_size_align_unchecked(size, 16)),
+            size,
+        )
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn init_task() {
     cycle::init();
     syscall::console_write(b"init task started\n");
-    let r = syscall::create_task(
+    let malloc_task_stack = unsafe {
+        let start = local_address_of!("__malloc_task_stack_start");
+        let end = local_address_of!("__malloc_task_stack_end");
+        slice::from_raw_parts_mut(start as *mut u8, (end - start) as usize)
+    };
+    let r = syscall::create_task_with_stack(
         tid::MALLOC_TASK_TID,
         local_address_of!("malloc_task"),
-        local_address_of!("__malloc_task_stack_end"),
+        malloc_task_stack,
     );
     if r.is_err() {
         syscall::console_write(b"create malloc task failed\n");
     }
-    let console_task_sp =
-        unsafe { alloc::alloc(Layout::from_size_align_unchecked(4096, 4)).add(4096) as u32 };
-    let r = syscall::create_task(
+    let r = syscall::create_task_with_stack(
         tid::CONSOLE_TASK_TID,
         local_address_of!("console_task"),
-        console_task_sp,
+        alloc_stack(4096),
     );
     if r.is_err() {
         syscall::console_write(b"create console task failed\n");
     }
+    // Let the servers preempt the demo tasks below.
+    for server_tid in [tid::MALLOC_TASK_TID, tid::CONSOLE_TASK_TID] {
+        if syscall::schedule_task(server_tid, SERVER_TASK_PRIORITY, 0).is_err() {
+            print_error!(b"schedule task {} failed\n", server_tid);
+        }
+    }
     let next_user_task = tid::USER_TASK_START_TID;
-    let print1_task_sp =
-        unsafe { alloc::alloc(Layout::from_size_align_unchecked(4096, 4)).add(4096) as u32 };
-    let r = syscall::create_task(
+    let r = syscall::create_task_with_stack(
         next_user_task,
         local_address_of!("print1_task"),
-        print1_task_sp,
+        alloc_stack(4096),
     );
     if r.is_err() {
         syscall::console_write(b"create print1 task failed\n");
     }
     // next_user_task += 1;
+    #[cfg(feature = "ipc-bench")]
+    for (tid, entry) in [
+        (IPC_BENCH_SERVER_TID, local_address_of!("ipc_bench_server")),
+        (IPC_BENCH_TASK_TID, local_address_of!("ipc_bench_task")),
+    ] {
+        if syscall::create_task_with_stack(tid, entry, alloc_stack(4096)).is_err() {
+            print_error!(b"create ipc bench task {} failed\n", tid);
+        }
+    }
     print2_task()
 }
 
-pub struct ConsolePayload {
-    data: *const u8,
-    len: usize,
-}
-
+// The text is transferred as the out-of-line payload.
 pub struct ConsoleMessage;
 
 impl ConsoleMessage {
     pub const CONSOLE_OUT: MessageType = MessageType(2);
+    pub const MAX_TEXT_LEN: usize = 128;
 
+    // The text stays valid until the receive buffer is reused.
     pub fn text_of(message: &Message) -> &[u8] {
-        unsafe {
-            let payload: &ConsolePayload = &*(message.raw.as_ptr() as *const ConsolePayload);
-            slice::from_raw_parts(payload.data, payload.len)
-        }
+        unsafe { slice::from_raw_parts(message.ool_ptr as *const u8, message.ool_len) }
     }
 
-    pub fn new(payload: &[u8]) -> Message {
-        Message {
+    pub fn new(text: &[u8]) -> Message {
+        let mut message = Message {
             message_type: ConsoleMessage::CONSOLE_OUT,
-            src_tid: 0,
-            raw: unsafe {
-                *(&ConsolePayload {
-                    data: payload.as_ptr(),
-                    len: payload.len(),
-                } as *const ConsolePayload as *const [u8; 24])
-            },
-        }
+            ..Message::zeroed()
+        };
+        message.set_ool(text);
+        message
     }
 }
 
@@ -117,8 +142,9 @@ pub struct AlignedVarArray<'a> {
 
 // pub fn console_task_rust() {
 //     syscall::console_write(b"console task started\n");
+//     let mut text = [0; ConsoleMessage::MAX_TEXT_LEN];
 //     loop {
-//         match syscall::ipc_recv(0) {
+//         match syscall::ipc_recv_ool(0, &mut text) {
 //             KResult::Ok(message) => {
 //                 syscall::console_write(ConsoleMessage::text_of(&message));
 //             }
@@ -131,8 +157,8 @@ pub struct AlignedVarArray<'a> {
 pub extern "C" fn print1_task() {
     syscall::console_write(b"print1 task started\n");
     loop {
-        let message = ConsoleMessage::new(b"Hello, Resea\n");
-        match syscall::ipc_send(tid::CONSOLE_TASK_TID, &message) {
+        let mut message = ConsoleMessage::new(b"Hello, Resea\n");
+        match syscall::ipc_send(tid::CONSOLE_TASK_TID, &mut message) {
             KResult::Ok(_) => (),
             err => print_error!(b"ipc_send failed: {}\n", err.err_as_u32()),
         };
@@ -144,8 +170,8 @@ pub fn print2_task() {
     syscall::console_write(b"print2 task started\n");
     cycle::wait(cycle::clock_hz() / 2);
     loop {
-        let message = ConsoleMessage::new(b"Hello, RISC-V\n");
-        match syscall::ipc_send(tid::CONSOLE_TASK_TID, &message) {
+        let mut message = ConsoleMessage::new(b"Hello, RISC-V\n");
+        match syscall::ipc_send(tid::CONSOLE_TASK_TID, &mut message) {
             KResult::Ok(_) => (),
             err => print_error!(b"ipc_send failed: {}\n", err.err_as_u32()),
         };
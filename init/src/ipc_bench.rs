@@ -0,0 +1,59 @@
+use ::syscall::{print_error, print_info};
+use ipc::tid;
+use klib::cycle;
+use klib::ipc::{Message, MessageType};
+use klib::result::KResult;
+use syscall::syscall;
+
+pub const IPC_BENCH_SERVER_TID: u32 = tid::USER_TASK_START_TID + 1;
+pub const IPC_BENCH_TASK_TID: u32 = tid::USER_TASK_START_TID + 2;
+
+const IPC_BENCH: MessageType = MessageType(3);
+const NUM_ROUND_TRIPS: u32 = 1000;
+
+fn bench_message() -> Message {
+    Message {
+        message_type: IPC_BENCH,
+        ..Message::zeroed()
+    }
+}
+
+// Replies to every message with an empty one.
+#[no_mangle]
+pub extern "C" fn ipc_bench_server() {
+    loop {
+        match syscall::ipc_recv(0) {
+            KResult::Ok(message) => match syscall::ipc_reply(message.src_tid, &bench_message()) {
+                KResult::Ok(()) => (),
+                err => print_error!(b"ipc_reply failed: {}\n", err.err_as_u32()),
+            },
+            err => print_error!(b"ipc_recv failed: {}\n", err.err_as_u32()),
+        }
+    }
+}
+
+// Measures the cycles of an `ipc_call` round trip to `ipc_bench_server`.
+#[no_mangle]
+pub extern "C" fn ipc_bench_task() {
+    // Warm up the caches.
+    for _ in 0..10 {
+        match syscall::ipc_call(IPC_BENCH_SERVER_TID, &mut bench_message()) {
+            KResult::Ok(_) => (),
+            err => {
+                print_error!(b"ipc_call failed: {}\n", err.err_as_u32());
+                syscall::exit_task();
+            }
+        }
+    }
+
+    let start = cycle::read_cycle();
+    for _ in 0..NUM_ROUND_TRIPS {
+        let _ = syscall::ipc_call(IPC_BENCH_SERVER_TID, &mut bench_message());
+    }
+    let cycles = cycle::read_cycle() - start;
+    print_info!(
+        b"ipc round trip: {} cycles\n",
+        (cycles / NUM_ROUND_TRIPS as u64) as u32
+    );
+    syscall::exit_task();
+}
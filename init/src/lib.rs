@@ -1,10 +1,9 @@
-#![no_std]
+#![cfg_attr(not(any(test, feature = "hosted")), no_std)]
 #![feature(concat_bytes)]
 #![feature(maybe_uninit_slice)]
 #![feature(asm_const)]
 #![feature(coroutines, coroutine_trait)]
 #![feature(core_intrinsics)]
-#![feature(maybe_uninit_uninit_array)]
 #![feature(sync_unsafe_cell)]
 #![feature(alloc_error_handler)]
 
@@ -14,13 +13,18 @@ extern crate syscall;
 
 mod generator;
 pub mod init;
+#[cfg(feature = "ipc-bench")]
+pub mod ipc_bench;
 
+#[cfg(not(any(test, feature = "hosted")))]
 use core::panic::PanicInfo;
+#[cfg(not(any(test, feature = "hosted")))]
 #[panic_handler]
 fn panic(_info: &PanicInfo) -> ! {
     loop {}
 }
 
+#[cfg(not(any(test, feature = "hosted")))]
 #[alloc_error_handler]
 fn alloc_error(_: core::alloc::Layout) -> ! {
     loop {}
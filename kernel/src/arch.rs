@@ -1,25 +1,77 @@
 pub mod console;
+pub mod diag;
 pub mod interrupt;
 pub mod irq;
 pub mod task;
+pub mod timer;
 
+// The RISC-V core. The devices (console, irq and timer) are replaced with the
+// ones in `qemu_virt` if the qemu-virt feature is enabled.
+//
+// Despite the name, `cramp32` (the module and the cargo feature) is the
+// support for the core shared by all boards: boot, traps, task switching, CSRs
+// and PMP. The QEMU virt machine is a board on top of it rather than another
+// arch, so it's built with both the cramp32 and the qemu-virt features (see
+// BOARD in Makefile).
 #[cfg(all(target_arch = "riscv32", feature = "cramp32"))]
 mod cramp32 {
+    #[cfg(not(feature = "qemu-virt"))]
     pub mod console;
     mod csr;
+    pub mod diag;
     mod init;
     pub mod interrupt;
+    #[cfg(not(feature = "qemu-virt"))]
     pub mod irq;
+    #[cfg(feature = "umode")]
+    mod pmp;
+    mod program;
     pub mod task;
-    mod timer;
+    #[cfg(not(feature = "qemu-virt"))]
+    pub mod timer;
     mod trap;
+    #[cfg(not(feature = "qemu-virt"))]
+    mod uart;
+
+    #[cfg(feature = "qemu-virt")]
+    pub use crate::arch::qemu_virt::{console, irq, timer};
+}
+
+#[cfg(all(target_arch = "riscv32", feature = "cramp32", feature = "qemu-virt"))]
+mod qemu_virt {
+    pub mod console;
+    pub mod irq;
+    pub mod timer;
     mod uart;
 }
 
 #[cfg(all(target_arch = "riscv32", feature = "cramp32"))]
 mod utilize {
     pub use crate::arch::cramp32::console;
+    pub use crate::arch::cramp32::diag;
     pub use crate::arch::cramp32::interrupt;
     pub use crate::arch::cramp32::irq;
     pub use crate::arch::cramp32::task;
+    pub use crate::arch::cramp32::timer;
+}
+
+// Runs the kernel on the host in tests. See mock/task.rs.
+#[cfg(test)]
+pub mod mock {
+    pub mod console;
+    pub mod diag;
+    pub mod interrupt;
+    pub mod irq;
+    pub mod task;
+    pub mod timer;
+}
+
+#[cfg(test)]
+mod utilize {
+    pub use crate::arch::mock::console;
+    pub use crate::arch::mock::diag;
+    pub use crate::arch::mock::interrupt;
+    pub use crate::arch::mock::irq;
+    pub use crate::arch::mock::task;
+    pub use crate::arch::mock::timer;
 }
@@ -1,5 +1,7 @@
 use super::interrupt;
 use super::irq;
+#[cfg(feature = "umode")]
+use super::pmp;
 use klib::local_address_of;
 
 #[macro_export]
@@ -50,6 +52,17 @@ macro_rules! cramp32_csrc {
     }
 }
 
+#[macro_export]
+macro_rules! cramp32_csrr {
+    ($reg: expr $(,)?) => {{
+        let value: u32;
+        unsafe {
+            core::arch::asm!(concat!("csrr {0}, ", $reg), out(reg) value);
+        }
+        value
+    }};
+}
+
 #[macro_export]
 macro_rules! cramp32_csrw {
     ($reg: expr, $val: expr $(,)?) => {
@@ -65,4 +78,10 @@ pub fn init_csr() {
     irq::init();
     interrupt::init_interrupt();
     interrupt::enable_machine_external_and_timer_interrupt();
+    #[cfg(feature = "umode")]
+    {
+        // Allow rdcycle, rdtime and rdinstret in U-mode (klib::cycle).
+        cramp32_csrw!("mcounteren", 7);
+        pmp::init();
+    }
 }
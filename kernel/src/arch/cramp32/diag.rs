@@ -0,0 +1,75 @@
+use super::task;
+use crate::arch::diag::ArchDiag;
+use crate::symbols::Symbolized;
+use crate::task::{TaskOps, TaskRef};
+use crate::{cramp32_csrr, printk};
+use core::arch::asm;
+use core::slice;
+
+// Registers in the order saved by `intr_handler` in trap.S. Note that a0 and
+// a1 are not saved on system calls.
+const TRAP_FRAME_REGS: [&[u8]; 16] = [
+    b"a1", b"a0", b"t6", b"t5", b"t4", b"t3", b"t2", b"t1", b"t0", b"a7", b"a6", b"a5", b"a4",
+    b"a3", b"a2", b"ra",
+];
+const TRAP_FRAME_SIZE: u32 = 64;
+const BACKTRACE_MAX_DEPTH: usize = 32;
+
+pub struct Diag;
+
+impl ArchDiag for Diag {
+    fn dump_trap_frame(task: TaskRef) {
+        // The trap frame is always at the top of the kernel stack.
+        let (_, stack_top) = task::kernel_stack_of(task.tid());
+        let frame = unsafe {
+            slice::from_raw_parts(
+                (stack_top - TRAP_FRAME_SIZE) as *const u32,
+                TRAP_FRAME_REGS.len(),
+            )
+        };
+        let mepc = cramp32_csrr!("mepc");
+        printk!(
+            b"mcause={} mepc={} ({}) mtval={}\n",
+            cramp32_csrr!("mcause") as usize,
+            mepc as usize,
+            Symbolized(mepc),
+            cramp32_csrr!("mtval") as usize
+        );
+        printk!(
+            b"user sp={} tp={}\n",
+            task.user_sp() as usize,
+            task.user_tp() as usize
+        );
+        for (i, (&name, &value)) in TRAP_FRAME_REGS.iter().zip(frame).enumerate() {
+            let separator: &[u8] = if i % 4 == 3 { b"\n" } else { b" " };
+            printk!(b" {}={}{}", name, value as usize, separator);
+        }
+        let ra = frame[TRAP_FRAME_REGS.len() - 1];
+        printk!(b"ra: {}\n", Symbolized(ra));
+    }
+
+    // Requires `-C force-frame-pointers=yes`: `s0` points to the caller's stack
+    // pointer, and the return address and the caller's `s0` are saved right below.
+    fn backtrace(f: &mut dyn FnMut(u32)) {
+        let (stack_bottom, stack_top) = task::kernel_stacks();
+        let mut fp: u32;
+        unsafe {
+            asm!("mv {0}, s0", out(reg) fp);
+        }
+        for _ in 0..BACKTRACE_MAX_DEPTH {
+            if fp < stack_bottom + 8 || fp > stack_top || fp % 4 != 0 {
+                break;
+            }
+            let ra = unsafe { *((fp - 4) as *const u32) };
+            let prev_fp = unsafe { *((fp - 8) as *const u32) };
+            if ra == 0 {
+                break;
+            }
+            f(ra);
+            if prev_fp <= fp {
+                break;
+            }
+            fp = prev_fp;
+        }
+    }
+}
@@ -4,9 +4,15 @@ use crate::boot::kmain;
 use klib::{local_address_of, mmio};
 
 fn init_bss() {
-    let bss_start = local_address_of!("__bss_start");
-    let bss_end = local_address_of!("__bss_end");
-    mmio::mzero_align4(bss_start as *mut u32, bss_end as *const u32);
+    let kernel_bss_start = local_address_of!("__kernel_bss_start");
+    let kernel_bss_end = local_address_of!("__kernel_bss_end");
+    mmio::mzero_align4(kernel_bss_start as *mut u32, kernel_bss_end as *const u32);
+    let init_bss_start = local_address_of!("__init_bss_start");
+    let init_bss_end = local_address_of!("__init_bss_end");
+    mmio::mzero_align4(init_bss_start as *mut u32, init_bss_end as *const u32);
+    let malloc_bss_start = local_address_of!("__malloc_bss_start");
+    let malloc_bss_end = local_address_of!("__malloc_bss_end");
+    mmio::mzero_align4(malloc_bss_start as *mut u32, malloc_bss_end as *const u32);
 }
 
 #[no_mangle]
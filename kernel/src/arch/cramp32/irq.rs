@@ -2,7 +2,7 @@ use crate::arch::irq::ArchIrq;
 use klib::mmio;
 
 const REG_INTERRUPT_ENABLE: *mut u32 = 0x3000_4000 as *mut u32;
-//const REG_INTERRUPT_STATUS: *mut u32 = 0x3000_4004 as *mut u32;
+const REG_INTERRUPT_STATUS: *mut u32 = 0x3000_4004 as *mut u32;
 
 pub fn init() {
     mmio::writev(REG_INTERRUPT_ENABLE, 0);
@@ -24,4 +24,8 @@ impl ArchIrq for Irq {
             mmio::readv(REG_INTERRUPT_ENABLE) & !(1 << irq),
         );
     }
+
+    fn pending_irqs() -> u32 {
+        mmio::readv(REG_INTERRUPT_STATUS) & mmio::readv(REG_INTERRUPT_ENABLE)
+    }
 }
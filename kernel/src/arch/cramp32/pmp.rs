@@ -0,0 +1,95 @@
+use super::program;
+#[cfg(not(feature = "qemu-virt"))]
+use crate::cramp32_csrs;
+use crate::cramp32_csrw;
+
+const PMP_R: u32 = 1 << 0;
+const PMP_W: u32 = 1 << 1;
+const PMP_X: u32 = 1 << 2;
+const PMP_A_TOR: u32 = 1 << 3;
+#[cfg(not(feature = "qemu-virt"))]
+const PMP_A_NAPOT: u32 = 3 << 3;
+
+// The configuration registers (REG_CONFIG_CLOCK_HZ in klib::cycle). QEMU
+// doesn't have them.
+#[cfg(not(feature = "qemu-virt"))]
+const CONFIG_REGS_BASE: u32 = 0x4000_0000;
+#[cfg(not(feature = "qemu-virt"))]
+const CONFIG_REGS_SIZE: u32 = 0x1000;
+
+fn cfg(entry: u32, flags: u32) -> u32 {
+    flags << ((entry % 4) * 8)
+}
+
+#[cfg(not(feature = "qemu-virt"))]
+fn napot(base: u32, size: u32) -> u32 {
+    (base >> 2) | ((size >> 3) - 1)
+}
+
+// Restricts the memory accessible from U-mode. M-mode is not affected since
+// no entry is locked. The lowest-numbered matching entry wins, and a TOR entry
+// whose address is not above the one of the previous entry matches nothing.
+// The board must implement at least 12 entries.
+//
+//   entry 0:  (off)  start of the stack guard of the current task
+//   entry 1:  -      the stack guard
+//   entry 2:  RW     the rest of the stack
+//   entry 3:  (off)  start of the code of the program of the current task
+//   entry 4:  RX     the code of the program (see program.rs)
+//   entry 5:  (off)  start of the data of the program
+//   entry 6:  RW     the data and the bss of the program
+//   entry 7:  (off)  start of the read-only data
+//   entry 8:  R      .rodata, .got and .symbols
+//   entry 9:  (off)  start of the heap
+//   entry 10: RW     the heap shared by all tasks
+//   entry 11: R      the configuration registers
+//
+// The entries 0 to 6 are updated on task switches (see `set_task_regions`).
+// Any other access from U-mode, including the kernel data, the other tasks'
+// stacks and the MMIO registers, raises an access fault which is reported to
+// the pager of the task.
+pub fn init() {
+    let (readonly_start, readonly_end) = program::readonly();
+    let (shared_start, shared_end) = program::shared_data();
+    cramp32_csrw!("pmpaddr7", readonly_start >> 2);
+    cramp32_csrw!("pmpaddr8", readonly_end >> 2);
+    cramp32_csrw!("pmpaddr9", shared_start >> 2);
+    cramp32_csrw!("pmpaddr10", shared_end >> 2);
+    set_task_regions(0, 0, 0, (0, 0), (0, 0));
+    cramp32_csrw!(
+        "pmpcfg0",
+        cfg(1, PMP_A_TOR) | cfg(2, PMP_A_TOR | PMP_R | PMP_W)
+    );
+    cramp32_csrw!(
+        "pmpcfg1",
+        cfg(4, PMP_A_TOR | PMP_R | PMP_X) | cfg(6, PMP_A_TOR | PMP_R | PMP_W)
+    );
+    cramp32_csrw!(
+        "pmpcfg2",
+        cfg(8, PMP_A_TOR | PMP_R) | cfg(10, PMP_A_TOR | PMP_R | PMP_W)
+    );
+    #[cfg(not(feature = "qemu-virt"))]
+    {
+        cramp32_csrw!("pmpaddr11", napot(CONFIG_REGS_BASE, CONFIG_REGS_SIZE));
+        cramp32_csrs!("pmpcfg2", cfg(11, PMP_A_NAPOT | PMP_R));
+    }
+}
+
+// Sets the regions of the next task on task switches: the stack guard
+// [guard, stack), the stack [stack, stack_end), and the code and the data of
+// its program. Empty ranges deny the access.
+pub fn set_task_regions(
+    guard: u32,
+    stack: u32,
+    stack_end: u32,
+    code: (u32, u32),
+    data: (u32, u32),
+) {
+    cramp32_csrw!("pmpaddr0", guard >> 2);
+    cramp32_csrw!("pmpaddr1", stack >> 2);
+    cramp32_csrw!("pmpaddr2", stack_end >> 2);
+    cramp32_csrw!("pmpaddr3", code.0 >> 2);
+    cramp32_csrw!("pmpaddr4", code.1 >> 2);
+    cramp32_csrw!("pmpaddr5", data.0 >> 2);
+    cramp32_csrw!("pmpaddr6", data.1 >> 2);
+}
@@ -0,0 +1,82 @@
+use klib::local_address_of;
+
+// The programs linked into the image (see sections.ld). A user task runs the
+// program containing its entry point, and may only execute the code and
+// access the data of that program (see pmp.rs).
+#[derive(Clone, Copy, PartialEq)]
+pub enum Program {
+    Init,
+    Malloc,
+}
+
+impl Program {
+    // Returns the program whose own code contains `pc`.
+    pub fn of(pc: u32) -> Option<Program> {
+        [Program::Init, Program::Malloc]
+            .into_iter()
+            .find(|program| {
+                let (start, end) = program.own_code();
+                pc >= start && pc < end
+            })
+    }
+
+    fn own_code(self) -> (u32, u32) {
+        match self {
+            Program::Init => (
+                local_address_of!("__init_text_start"),
+                local_address_of!("__shared_text_start"),
+            ),
+            Program::Malloc => (
+                local_address_of!("__shared_text_end"),
+                local_address_of!("__malloc_text_end"),
+            ),
+        }
+    }
+
+    // Returns [start, end) of the code executable by the program: its own
+    // code and the code shared by the programs.
+    pub fn code(self) -> (u32, u32) {
+        match self {
+            Program::Init => (
+                local_address_of!("__init_text_start"),
+                local_address_of!("__shared_text_end"),
+            ),
+            Program::Malloc => (
+                local_address_of!("__shared_text_start"),
+                local_address_of!("__malloc_text_end"),
+            ),
+        }
+    }
+
+    // Returns [start, end) of the data and the bss of the program.
+    pub fn data(self) -> (u32, u32) {
+        match self {
+            Program::Init => (
+                local_address_of!("__init_data_start"),
+                local_address_of!("__init_data_end"),
+            ),
+            Program::Malloc => (
+                local_address_of!("__malloc_data_start"),
+                local_address_of!("__malloc_data_end"),
+            ),
+        }
+    }
+}
+
+// Returns [start, end) of the read-only data (.rodata, .got and .symbols)
+// readable by all programs.
+pub fn readonly() -> (u32, u32) {
+    (
+        local_address_of!("__text_end"),
+        local_address_of!("__readonly_end"),
+    )
+}
+
+// Returns [start, end) of the heap shared by all programs, including the word
+// below `__heap_start` used by malloc.
+pub fn shared_data() -> (u32, u32) {
+    (
+        local_address_of!("__shared_data_start"),
+        local_address_of!("__memory_end"),
+    )
+}
@@ -1,6 +1,10 @@
+#[cfg(feature = "umode")]
+use super::pmp;
+use super::program::{self, Program};
 use crate::arch::task::ArchTask;
 use crate::config;
-use crate::task::{GetNoarchTask, NoarchTask};
+use crate::task::{self, GetNoarchTask, NoarchTask, TaskOps, TaskType};
+use crate::{cramp32_csrc, cramp32_csrs};
 use core::arch::asm;
 use core::cell::Cell;
 use core::{mem, slice};
@@ -9,6 +13,11 @@ use klib::result::KResult;
 
 const STACK_SIZE: usize = 512;
 const STACK_COUNT: usize = STACK_SIZE / 4;
+pub(super) const MSTATUS_MPP: u32 = 3 << 11;
+// Written at the bottom of each kernel stack. Overwritten on overflows.
+const STACK_CANARY: u32 = 0xdead_ca11;
+// Fills the unused part of each kernel stack to measure its peak usage.
+const STACK_FILL: u32 = 0xa5a5_a5a5;
 
 struct KernelStack {
     stack: [[u32; STACK_COUNT]; config::NUM_TASKS as usize],
@@ -19,17 +28,27 @@ static mut KERNEL_STACKS: KernelStack = KernelStack {
     stack: [[0; STACK_COUNT]; config::NUM_TASKS as usize],
 };
 
-#[repr(C, align(128))]
+// The size must be a power of two as `LinkAdapter::from_link` rounds a link
+// address down to the task containing it.
+#[repr(C, align(256))]
 pub struct Task {
     stack: Cell<u32>,
     user_sp: Cell<u32>,
     user_tp: Cell<u32>,
+    // The program run by the task. None for the idle task.
+    program: Cell<Option<Program>>,
     noarch_task: NoarchTask,
 }
 
+const _: () = assert!(mem::size_of::<Task>() == 256);
+
 fn init_stack(tid: u32, pc: u32) -> u32 {
     unsafe {
         let stack: *mut u32 = KERNEL_STACKS.stack.get_unchecked_mut(tid as usize) as *mut u32;
+        *stack = STACK_CANARY;
+        for i in 1..STACK_COUNT - 16 {
+            *stack.add(i) = STACK_FILL;
+        }
         let sp = stack.add(STACK_COUNT).sub(16);
         let prep = slice::from_raw_parts_mut(sp, 16);
         let cramp32_start_task_ptr = local_address_of!("cramp32_start_task");
@@ -44,13 +63,86 @@ fn init_stack(tid: u32, pc: u32) -> u32 {
     }
 }
 
+// mret returns to the privilege mode in mstatus.MPP. With the umode feature,
+// user tasks run in U-mode. The idle task always runs in M-mode since wfi may
+// trap in U-mode.
+fn set_previous_privilege(task: &Task) {
+    if cfg!(feature = "umode") && task.task_type() != TaskType::Idle {
+        cramp32_csrc!("mstatus", MSTATUS_MPP);
+    } else {
+        cramp32_csrs!("mstatus", MSTATUS_MPP);
+    }
+}
+
+// Returns the bottom of the registered user stack of the task, the top of the
+// guard at its bottom, and its top. All zero if it has none.
+fn user_stack_layout(task: &Task) -> (u32, u32, u32) {
+    let (bottom, size) = task.user_stack();
+    let guard_size = if size == 0 {
+        0
+    } else {
+        config::USER_STACK_GUARD_SIZE
+    };
+    (bottom, bottom + guard_size, bottom + size)
+}
+
+// Allows the task to access its own stack except the guard at the bottom, and
+// the code and the data of its program (see pmp.rs).
+#[cfg(feature = "umode")]
+fn set_memory_regions(task: &Task) {
+    let (guard, stack, stack_end) = user_stack_layout(task);
+    let (code, data) = match task.program.get() {
+        Some(program) => (program.code(), program.data()),
+        None => ((0, 0), (0, 0)),
+    };
+    pmp::set_task_regions(guard, stack, stack_end, code, data);
+}
+
+// Returns the bottom and the top of the kernel stack of the task.
+pub fn kernel_stack_of(tid: u32) -> (u32, u32) {
+    unsafe {
+        let stack = KERNEL_STACKS.stack.get_unchecked(tid as usize);
+        let bottom = stack.as_ptr() as u32;
+        (bottom, bottom + STACK_SIZE as u32)
+    }
+}
+
+fn kernel_stack(task: &Task) -> &[u32; STACK_COUNT] {
+    unsafe { KERNEL_STACKS.stack.get_unchecked(task.tid() as usize) }
+}
+
+// Called on every trap exit.
+#[no_mangle]
+pub extern "C" fn cramp32_stack_check() {
+    task::stack_check(Task::current());
+}
+
+// Returns the range covering the kernel stacks of all tasks.
+pub fn kernel_stacks() -> (u32, u32) {
+    unsafe {
+        let bottom = KERNEL_STACKS.stack.as_ptr() as u32;
+        (bottom, bottom + mem::size_of::<KernelStack>() as u32)
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn idle_task() {
-    loop {}
+    loop {
+        // Sleep until the next interrupt. The timer interrupt is programmed
+        // to the next deadline while the idle task is running.
+        unsafe {
+            asm!("wfi");
+        }
+    }
 }
 
 impl ArchTask for Task {
     fn arch_task_init(tid: u32, task: &Task, pc: u32, sp: u32) -> KResult<()> {
+        let program = Program::of(pc);
+        if program.is_none() && pc != Self::arch_idle_task_entry_point() {
+            return KResult::InvalidArg;
+        }
+        task.program.set(program);
         task.stack.set(init_stack(tid, pc));
         task.user_sp.set(sp);
         task.user_tp.set(0);
@@ -66,6 +158,9 @@ impl ArchTask for Task {
             #[allow(improper_ctypes)]
             fn cramp32_task_switch(prev_sp: *mut u32, next_sp: u32, next_task: *const Task);
         }
+        set_previous_privilege(next);
+        #[cfg(feature = "umode")]
+        set_memory_regions(next);
         unsafe {
             cramp32_task_switch(prev.stack.as_ptr(), next.stack.get(), next);
         }
@@ -76,6 +171,7 @@ impl ArchTask for Task {
             #[allow(improper_ctypes)]
             fn cramp32_switch_idle_task(dummy: u32, next_sp: u32, next_task: *const Task);
         }
+        set_previous_privilege(idle_task);
         unsafe {
             cramp32_switch_idle_task(0, idle_task.stack.get(), idle_task);
         }
@@ -88,6 +184,49 @@ impl ArchTask for Task {
             &*mem::transmute::<u32, *const Task>(task_ptr)
         }
     }
+
+    // The same regions as the ones accessible from the task in U-mode (see
+    // pmp.rs): it may write its own stack, the data of its program and the
+    // heap, and read the code of its program and the read-only data.
+    fn arch_user_access_ok(task: &Task, addr: u32, len: u32, write: bool) -> bool {
+        let program = match task.program.get() {
+            Some(program) => program,
+            None => return false,
+        };
+        let end = match addr.checked_add(len) {
+            Some(end) => end,
+            None => return false,
+        };
+        let within = |(start, region_end): (u32, u32)| addr >= start && end <= region_end;
+        let (_, stack, stack_end) = user_stack_layout(task);
+        within((stack, stack_end))
+            || within(program.data())
+            || within(program::shared_data())
+            || (!write && (within(program.code()) || within(program::readonly())))
+    }
+
+    fn arch_stack_canary_ok(task: &Task) -> bool {
+        kernel_stack(task)[0] == STACK_CANARY
+    }
+
+    fn arch_kernel_stack_peak(task: &Task) -> u32 {
+        let stack = kernel_stack(task);
+        if stack[0] != STACK_CANARY {
+            return STACK_SIZE as u32;
+        }
+        let unused = stack[1..].iter().take_while(|w| **w == STACK_FILL).count() + 1;
+        ((STACK_COUNT - unused) * 4) as u32
+    }
+}
+
+impl Task {
+    pub fn user_sp(&self) -> u32 {
+        self.user_sp.get()
+    }
+
+    pub fn user_tp(&self) -> u32 {
+        self.user_tp.get()
+    }
 }
 
 impl GetNoarchTask for Task {
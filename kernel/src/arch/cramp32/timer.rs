@@ -1,3 +1,4 @@
+use crate::arch::timer::ArchTimer;
 use klib::mmio;
 
 const REG_MTIMER_MTIME_LO: *mut u32 = 0x3000_2000 as *mut u32;
@@ -10,11 +11,14 @@ const REG_CONFIG_CLOCK_HZ: *mut u32 = 0x4000_0004 as *mut u32;
 static mut TIMER: MachineTimer = MachineTimer {
     next_tick: 0,
     tick_span: 0,
+    periodic: false,
 };
 
 pub struct MachineTimer {
     next_tick: u64,
     tick_span: u32,
+    // false while mtimecmp is programmed as a one-shot deadline.
+    periodic: bool,
 }
 
 pub fn init() {
@@ -29,7 +33,7 @@ pub fn reload() {
     }
 }
 
-impl MachineTimer {
+impl ArchTimer for MachineTimer {
     fn read_mtime() -> u64 {
         let mut mtime_lo;
         let mut mtime_hi;
@@ -42,26 +46,57 @@ impl MachineTimer {
         }
     }
 
+    fn mtime_hz() -> u32 {
+        mmio::readv(REG_CONFIG_CLOCK_HZ)
+    }
+
+    fn start_tick() {
+        unsafe {
+            if !TIMER.periodic {
+                TIMER.periodic = true;
+                TIMER.next_tick = Self::read_mtime();
+                TIMER.reload();
+            }
+        }
+    }
+
+    fn set_oneshot(deadline: u64) {
+        unsafe {
+            TIMER.periodic = false;
+        }
+        Self::write_mtimecmp(deadline);
+    }
+}
+
+impl MachineTimer {
     pub fn init() -> Self {
-        let clock_hz = mmio::readv(REG_CONFIG_CLOCK_HZ);
-        let tick_span = clock_hz / 1000;
+        let tick_span = Self::mtime_hz() / 1000;
         let next_tick = Self::read_mtime();
         let mut timer = MachineTimer {
             next_tick,
             tick_span,
+            periodic: true,
         };
         timer.reload();
         timer
     }
 
+    fn write_mtimecmp(value: u64) {
+        // Avoid a spurious interrupt while updating the lower half.
+        mmio::writev(REG_MTIMER_MTIMECMP_HI, u32::MAX);
+        mmio::writev(REG_MTIMER_MTIMECMP_LO, (value & 0xffff_ffffu64) as u32);
+        mmio::writev(REG_MTIMER_MTIMECMP_HI, (value >> 32) as u32);
+    }
+
     pub fn reload(&mut self) {
+        if !self.periodic {
+            // The one-shot deadline has been reached. The kernel programs the
+            // next one after handling expired timers.
+            Self::write_mtimecmp(u64::MAX);
+            return;
+        }
         self.next_tick = self.next_tick + self.tick_span as u64;
         // TODO compare next_tick and mtime
-        mmio::writev(REG_MTIMER_MTIMECMP_HI, u32::MAX);
-        mmio::writev(
-            REG_MTIMER_MTIMECMP_LO,
-            (self.next_tick & 0xffff_ffffu64) as u32,
-        );
-        mmio::writev(REG_MTIMER_MTIMECMP_HI, (self.next_tick >> 32) as u32);
+        Self::write_mtimecmp(self.next_tick);
     }
 }
@@ -1,9 +1,31 @@
+use super::task::MSTATUS_MPP;
 use super::timer;
-use crate::{kpanic, task};
+use crate::{cramp32_csrr, exception, irq, kpanic, task};
+use klib::local_address_of;
 
 #[no_mangle]
 pub extern "C" fn cramp32_handle_exception() {
-    kpanic!(b"Unexpected exception\n");
+    let pc = cramp32_csrr!("mepc");
+    exception::handle_exception(
+        cramp32_csrr!("mcause"),
+        cramp32_csrr!("mtval"),
+        pc,
+        raised_by_task(pc),
+    );
+}
+
+// Whether the exception was raised by a task rather than by the kernel. With
+// umode, mstatus.MPP holds the privilege mode before the trap. Without it,
+// tasks run in M-mode too and only the faulting pc tells them apart (a fault
+// in code shared with tasks, e.g. memcpy, is blamed on the task).
+fn raised_by_task(pc: u32) -> bool {
+    if cfg!(feature = "umode") {
+        cramp32_csrr!("mstatus") & MSTATUS_MPP == 0
+    } else {
+        let start = local_address_of!("__kernel_text_start");
+        let end = local_address_of!("__kernel_text_end");
+        pc < start || pc >= end
+    }
 }
 
 #[no_mangle]
@@ -13,6 +35,9 @@ pub extern "C" fn cramp32_handle_interrupt(mcause: u32) {
             timer::reload();
             task::handle_timer_irq();
         }
+        0x8000000B => {
+            irq::handle_external_irq();
+        }
         _ => {
             kpanic!(b"unimplemented!\n");
         }
@@ -0,0 +1,17 @@
+use crate::task::TaskRef;
+
+pub trait ArchDiag {
+    fn dump_trap_frame(task: TaskRef);
+    fn backtrace(f: &mut dyn FnMut(u32));
+}
+
+// Prints the registers saved on the last kernel entry of `task`.
+pub fn dump_trap_frame(task: TaskRef) {
+    <super::utilize::diag::Diag as ArchDiag>::dump_trap_frame(task);
+}
+
+// Calls `f` with each return address found by walking the frame pointers of the
+// current kernel stack.
+pub fn backtrace(f: &mut dyn FnMut(u32)) {
+    <super::utilize::diag::Diag as ArchDiag>::backtrace(f);
+}
@@ -0,0 +1,14 @@
+use crate::arch::console::ArchConsole;
+use std::io::{self, Write};
+
+pub struct Console;
+
+impl ArchConsole for Console {
+    fn print_char(ch: u8) {
+        let _ = io::stdout().write_all(&[ch]);
+    }
+
+    fn read_char() -> Option<u8> {
+        None
+    }
+}
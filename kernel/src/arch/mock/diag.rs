@@ -0,0 +1,10 @@
+use crate::arch::diag::ArchDiag;
+use crate::task::TaskRef;
+
+pub struct Diag;
+
+impl ArchDiag for Diag {
+    fn dump_trap_frame(_task: TaskRef) {}
+
+    fn backtrace(_f: &mut dyn FnMut(u32)) {}
+}
@@ -0,0 +1,9 @@
+use crate::arch::interrupt::ArchInterrupt;
+
+pub struct Interrupt;
+
+impl ArchInterrupt for Interrupt {
+    fn enable_interrupt() {}
+
+    fn disable_interrupt() {}
+}
@@ -0,0 +1,13 @@
+use crate::arch::irq::ArchIrq;
+
+pub struct Irq;
+
+impl ArchIrq for Irq {
+    fn enable_irq(_irq: u32) {}
+
+    fn disable_irq(_irq: u32) {}
+
+    fn pending_irqs() -> u32 {
+        0
+    }
+}
@@ -0,0 +1,208 @@
+use crate::arch::task::ArchTask;
+use crate::config;
+use crate::irq::{self, IrqTable};
+use crate::task::{self, GetNoarchTask, NoarchTask, TaskOps, TaskPool, TaskRef, TaskState};
+use crate::timer::{self, TimerQueue};
+use core::ptr;
+use klib::result::KResult;
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::thread;
+
+// Each task runs on its own thread, and only the thread of the current task
+// proceeds: the others wait in `arch_task_switch` until they are switched to.
+// The idle task runs on the thread of the test.
+
+// The size must be a power of two as `LinkAdapter::from_link` rounds a link
+// address down to the task containing it.
+#[repr(C, align(512))]
+pub struct Task {
+    noarch_task: NoarchTask,
+}
+
+const _: () = assert!(core::mem::size_of::<Task>() == 512);
+
+struct Cpu {
+    current: u32,
+    // Incremented on every task creation. A thread of a destroyed task never
+    // runs again even if its TID is reused.
+    generations: [u64; config::NUM_TASKS as usize],
+    next_generation: u64,
+    // (prev, next) TIDs of the context switches.
+    switches: Vec<(u32, u32)>,
+    // A panic in a task, rethrown in the idle task.
+    panic: Option<Box<dyn Any + Send>>,
+}
+
+static CPU: Mutex<Cpu> = Mutex::new(Cpu {
+    current: 0,
+    generations: [0; config::NUM_TASKS as usize],
+    next_generation: 1,
+    switches: Vec::new(),
+    panic: None,
+});
+static SWITCHED: Condvar = Condvar::new();
+// The memory regions accessible from the tasks in syscalls: (tid, start, end,
+// writable). Tasks can access none by default.
+static USER_REGIONS: Mutex<Vec<(u32, u32, u32, bool)>> = Mutex::new(Vec::new());
+// The kernel state is global: tests using it run one by one.
+static KERNEL: Mutex<()> = Mutex::new(());
+
+fn cpu() -> MutexGuard<'static, Cpu> {
+    CPU.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn switch_to(cpu: &mut Cpu, tid: u32) {
+    cpu.current = tid;
+    SWITCHED.notify_all();
+}
+
+fn wait_for(
+    mut cpu: MutexGuard<'static, Cpu>,
+    tid: u32,
+    generation: u64,
+) -> MutexGuard<'static, Cpu> {
+    while cpu.current != tid || cpu.generations[tid as usize] != generation {
+        cpu = SWITCHED.wait(cpu).unwrap_or_else(|e| e.into_inner());
+    }
+    cpu
+}
+
+// Runs `f` as the idle task on a freshly initialized kernel. Tasks spawned by
+// `spawn` run when `f` calls `TaskPool::task_switch` until all of them are
+// blocked or exited.
+pub fn run_kernel<R>(f: impl FnOnce() -> R) -> R {
+    let _kernel = KERNEL.lock().unwrap_or_else(|e| e.into_inner());
+    unsafe {
+        ptr::write_bytes(
+            task::get_task_pool() as *const TaskPool as *mut TaskPool,
+            0,
+            1,
+        );
+        ptr::write_bytes(
+            timer::get_timer_queue() as *const TimerQueue as *mut TimerQueue,
+            0,
+            1,
+        );
+        ptr::write_bytes(
+            irq::get_irq_table() as *const IrqTable as *mut IrqTable,
+            0,
+            1,
+        );
+    }
+    {
+        let mut cpu = cpu();
+        cpu.switches.clear();
+        cpu.panic = None;
+    }
+    user_regions().clear();
+
+    let task_pool = task::get_task_pool();
+    assert!(task_pool.create_idle_task().is_ok());
+    task_pool.switch_idle_task();
+    f()
+}
+
+// Creates a user task running `f`. The task exits when `f` returns.
+pub fn spawn(tid: u32, f: impl FnOnce() + Send + 'static) {
+    assert!(task::get_task_pool()
+        .create_user_task(tid, 0, 0, task::KERNEL_TID)
+        .is_ok());
+    let generation = cpu().generations[tid as usize];
+    thread::spawn(move || {
+        drop(wait_for(cpu(), tid, generation));
+        match panic::catch_unwind(AssertUnwindSafe(f)) {
+            Ok(()) => task::get_task_pool().exit_current_task(),
+            Err(payload) => {
+                let mut cpu = cpu();
+                cpu.panic = Some(payload);
+                switch_to(&mut cpu, 0);
+            }
+        }
+    });
+}
+
+fn user_regions() -> MutexGuard<'static, Vec<(u32, u32, u32, bool)>> {
+    USER_REGIONS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// Allows the task `tid` to access [start, start + len) in syscalls, for writing
+// if `writable` is true.
+pub fn add_user_region(tid: u32, start: u32, len: u32, writable: bool) {
+    user_regions().push((tid, start, start + len, writable));
+}
+
+// Returns the context switches so far as (prev, next) TIDs.
+pub fn switches() -> Vec<(u32, u32)> {
+    cpu().switches.clone()
+}
+
+impl ArchTask for Task {
+    fn arch_task_init(tid: u32, _task: TaskRef, _pc: u32, _sp: u32) -> KResult<()> {
+        user_regions().retain(|region| region.0 != tid);
+        let mut cpu = cpu();
+        cpu.generations[tid as usize] = cpu.next_generation;
+        cpu.next_generation += 1;
+        KResult::Ok(())
+    }
+
+    fn arch_idle_task_entry_point() -> u32 {
+        0
+    }
+
+    fn arch_task_switch(prev: &Task, next: &Task) {
+        let mut cpu = cpu();
+        cpu.switches.push((prev.tid(), next.tid()));
+        switch_to(&mut cpu, next.tid());
+        if prev.state() == TaskState::Unused {
+            // The task has exited. Its thread finishes.
+            return;
+        }
+
+        let generation = cpu.generations[prev.tid() as usize];
+        let mut cpu = wait_for(cpu, prev.tid(), generation);
+        if prev.tid() == 0 {
+            if let Some(payload) = cpu.panic.take() {
+                drop(cpu);
+                panic::resume_unwind(payload);
+            }
+        }
+    }
+
+    fn arch_switch_idle_task(_idle_task: TaskRef) {
+        switch_to(&mut cpu(), 0);
+    }
+
+    fn current() -> TaskRef {
+        let tid = cpu().current;
+        &task::get_task_pool().tasks[tid as usize]
+    }
+
+    // Checked as on the target against the regions added by `add_user_region`.
+    fn arch_user_access_ok(task: TaskRef, addr: u32, len: u32, write: bool) -> bool {
+        let end = match addr.checked_add(len) {
+            Some(end) => end,
+            None => return false,
+        };
+        user_regions()
+            .iter()
+            .any(|&(tid, start, region_end, writable)| {
+                tid == task.tid() && addr >= start && end <= region_end && (writable || !write)
+            })
+    }
+
+    fn arch_stack_canary_ok(_task: &Task) -> bool {
+        true
+    }
+
+    fn arch_kernel_stack_peak(_task: &Task) -> u32 {
+        0
+    }
+}
+
+impl GetNoarchTask for Task {
+    fn noarch(&self) -> &NoarchTask {
+        &self.noarch_task
+    }
+}
@@ -0,0 +1,27 @@
+use crate::arch::timer::ArchTimer;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// The machine timer only advances when tests call `advance`.
+static MTIME: AtomicU64 = AtomicU64::new(0);
+
+pub const MTIME_HZ: u32 = 1_000_000;
+
+pub fn advance(ms: u32) {
+    MTIME.fetch_add(ms as u64 * (MTIME_HZ / 1000) as u64, Ordering::SeqCst);
+}
+
+pub struct MachineTimer;
+
+impl ArchTimer for MachineTimer {
+    fn read_mtime() -> u64 {
+        MTIME.load(Ordering::SeqCst)
+    }
+
+    fn mtime_hz() -> u32 {
+        MTIME_HZ
+    }
+
+    fn start_tick() {}
+
+    fn set_oneshot(_deadline: u64) {}
+}
@@ -0,0 +1,48 @@
+use crate::arch::irq::ArchIrq;
+use crate::config;
+use klib::mmio;
+
+// The PLIC. The kernel runs in the M-mode context of the hart 0.
+const REG_PLIC_PRIORITY: *mut u32 = 0x0c00_0000 as *mut u32;
+const REG_PLIC_ENABLE: *mut u32 = 0x0c00_2000 as *mut u32;
+const REG_PLIC_THRESHOLD: *mut u32 = 0x0c20_0000 as *mut u32;
+const REG_PLIC_CLAIM: *mut u32 = 0x0c20_0004 as *mut u32;
+
+pub fn init() {
+    mmio::writev(REG_PLIC_ENABLE, 0);
+    mmio::writev(REG_PLIC_THRESHOLD, 0);
+    // The interrupt source 0 does not exist.
+    for irq in 1..config::NUM_IRQS {
+        unsafe {
+            mmio::writev(REG_PLIC_PRIORITY.add(irq as usize), 1);
+        }
+    }
+}
+
+pub struct Irq;
+
+impl ArchIrq for Irq {
+    fn enable_irq(irq: u32) {
+        mmio::writev(REG_PLIC_ENABLE, mmio::readv(REG_PLIC_ENABLE) | (1 << irq));
+    }
+
+    fn disable_irq(irq: u32) {
+        mmio::writev(REG_PLIC_ENABLE, mmio::readv(REG_PLIC_ENABLE) & !(1 << irq));
+    }
+
+    // Claims all pending interrupts. Each claimed line is masked before
+    // completing it so that a level-triggered device does not raise it
+    // again until the owner acknowledges it.
+    fn pending_irqs() -> u32 {
+        let mut pending = 0;
+        loop {
+            let irq = mmio::readv(REG_PLIC_CLAIM);
+            if irq == 0 {
+                return pending;
+            }
+            Self::disable_irq(irq);
+            mmio::writev(REG_PLIC_CLAIM, irq);
+            pending |= 1 << irq;
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use crate::arch::timer::ArchTimer;
+use klib::mmio;
+
+// The CLINT of the hart 0.
+const REG_CLINT_MTIMECMP_LO: *mut u32 = 0x0200_4000 as *mut u32;
+const REG_CLINT_MTIMECMP_HI: *mut u32 = 0x0200_4004 as *mut u32;
+const REG_CLINT_MTIME_LO: *mut u32 = 0x0200_bff8 as *mut u32;
+const REG_CLINT_MTIME_HI: *mut u32 = 0x0200_bffc as *mut u32;
+
+// The frequency of mtime on the virt machine.
+const MTIME_HZ: u32 = 10_000_000;
+
+static mut TIMER: MachineTimer = MachineTimer {
+    next_tick: 0,
+    tick_span: 0,
+    periodic: false,
+};
+
+pub struct MachineTimer {
+    next_tick: u64,
+    tick_span: u32,
+    // false while mtimecmp is programmed as a one-shot deadline.
+    periodic: bool,
+}
+
+pub fn init() {
+    unsafe {
+        TIMER = MachineTimer::init();
+    }
+}
+
+pub fn reload() {
+    unsafe {
+        TIMER.reload();
+    }
+}
+
+impl ArchTimer for MachineTimer {
+    fn read_mtime() -> u64 {
+        let mut mtime_lo;
+        let mut mtime_hi;
+        loop {
+            mtime_hi = mmio::readv(REG_CLINT_MTIME_HI);
+            mtime_lo = mmio::readv(REG_CLINT_MTIME_LO);
+            if mtime_hi == mmio::readv(REG_CLINT_MTIME_HI) {
+                return ((mtime_hi as u64) << 32) | mtime_lo as u64;
+            }
+        }
+    }
+
+    fn mtime_hz() -> u32 {
+        MTIME_HZ
+    }
+
+    fn start_tick() {
+        unsafe {
+            if !TIMER.periodic {
+                TIMER.periodic = true;
+                TIMER.next_tick = Self::read_mtime();
+                TIMER.reload();
+            }
+        }
+    }
+
+    fn set_oneshot(deadline: u64) {
+        unsafe {
+            TIMER.periodic = false;
+        }
+        Self::write_mtimecmp(deadline);
+    }
+}
+
+impl MachineTimer {
+    pub fn init() -> Self {
+        let tick_span = Self::mtime_hz() / 1000;
+        let next_tick = Self::read_mtime();
+        let mut timer = MachineTimer {
+            next_tick,
+            tick_span,
+            periodic: true,
+        };
+        timer.reload();
+        timer
+    }
+
+    fn write_mtimecmp(value: u64) {
+        // Avoid a spurious interrupt while updating the lower half.
+        mmio::writev(REG_CLINT_MTIMECMP_HI, u32::MAX);
+        mmio::writev(REG_CLINT_MTIMECMP_LO, (value & 0xffff_ffffu64) as u32);
+        mmio::writev(REG_CLINT_MTIMECMP_HI, (value >> 32) as u32);
+    }
+
+    pub fn reload(&mut self) {
+        if !self.periodic {
+            // The one-shot deadline has been reached. The kernel programs the
+            // next one after handling expired timers.
+            Self::write_mtimecmp(u64::MAX);
+            return;
+        }
+        self.next_tick += self.tick_span as u64;
+        Self::write_mtimecmp(self.next_tick);
+    }
+}
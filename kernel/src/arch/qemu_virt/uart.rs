@@ -0,0 +1,21 @@
+use klib::mmio::{readv, writev};
+
+// NS16550A.
+const REG_UART_RBR_THR: *mut u8 = 0x1000_0000 as *mut u8;
+const REG_UART_LSR: *mut u8 = 0x1000_0005 as *mut u8;
+
+const LSR_DATA_READY: u8 = 1 << 0;
+const LSR_THR_EMPTY: u8 = 1 << 5;
+
+pub fn tx(value: u8) {
+    while (readv(REG_UART_LSR) & LSR_THR_EMPTY) == 0 {}
+    writev(REG_UART_RBR_THR, value);
+}
+
+pub fn rx() -> Option<u8> {
+    if (readv(REG_UART_LSR) & LSR_DATA_READY) != 0 {
+        Some(readv(REG_UART_RBR_THR))
+    } else {
+        None
+    }
+}
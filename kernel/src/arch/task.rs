@@ -8,4 +8,11 @@ pub trait ArchTask {
     fn arch_task_switch(prev: &Task, next: &Task);
     fn arch_switch_idle_task(idle_task: TaskRef);
     fn current() -> TaskRef;
+    // Returns true if `task` is allowed to access [addr, addr + len) (for
+    // writing if `write` is true).
+    fn arch_user_access_ok(task: TaskRef, addr: u32, len: u32, write: bool) -> bool;
+    // Returns false if the canary at the bottom of the kernel stack is broken.
+    fn arch_stack_canary_ok(task: &Task) -> bool;
+    // Returns the maximum number of bytes ever used in the kernel stack.
+    fn arch_kernel_stack_peak(task: &Task) -> u32;
 }
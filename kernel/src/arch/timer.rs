@@ -0,0 +1,26 @@
+pub trait ArchTimer {
+    fn read_mtime() -> u64;
+    fn mtime_hz() -> u32;
+    fn start_tick();
+    fn set_oneshot(deadline: u64);
+}
+
+// Returns the current value of the free-running machine timer.
+pub fn read_mtime() -> u64 {
+    <super::utilize::timer::MachineTimer as ArchTimer>::read_mtime()
+}
+
+// Returns the number of machine timer counts per second.
+pub fn mtime_hz() -> u32 {
+    <super::utilize::timer::MachineTimer as ArchTimer>::mtime_hz()
+}
+
+// (Re)starts the periodic timer interrupt. Does nothing if it's already running.
+pub fn start_tick() {
+    <super::utilize::timer::MachineTimer as ArchTimer>::start_tick();
+}
+
+// Stops the periodic timer interrupt and fires a single one at `deadline`.
+pub fn set_oneshot(deadline: u64) {
+    <super::utilize::timer::MachineTimer as ArchTimer>::set_oneshot(deadline);
+}
@@ -1,14 +1,21 @@
 use crate::printk;
 use crate::task;
+use core::slice;
 use klib::local_address_of;
 
 pub fn kmain() {
     printk!(b"\nBooting Resea/Rust v0.0.1\n");
+    let init_task_stack = unsafe {
+        let start = local_address_of!("__init_task_stack_start");
+        let end = local_address_of!("__init_task_stack_end");
+        slice::from_raw_parts_mut(start as *mut u8, (end - start) as usize)
+    };
     if task::get_task_pool()
-        .create_user_task(
+        .create_user_task_with_stack(
             task::INIT_TID,
             local_address_of!("init_task"),
-            local_address_of!("__init_task_stack_end"),
+            init_task_stack,
+            task::KERNEL_TID,
         )
         .is_err()
     {
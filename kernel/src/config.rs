@@ -1 +1,5 @@
 pub const NUM_TASKS: u32 = 64;
+pub const NUM_IRQS: u32 = 32;
+pub const NUM_TIMERS_PER_TASK: u32 = 8;
+// The lowest bytes of a registered user stack which the task may not access.
+pub const USER_STACK_GUARD_SIZE: u32 = 16;
@@ -1,9 +1,9 @@
 #[macro_export]
 macro_rules! printk {
-    ($fmt:expr $(,$args:expr)*) => {
+    ($fmt:expr $(,$args:expr)*) => {{
         use klib::fmt::FormattedWriter;
         klib::make_args!($($args),*).format(&mut $crate::console::ConsoleWriter, $fmt)
-    }
+    }}
 }
 
 #[macro_export]
@@ -12,6 +12,33 @@ macro_rules! kpanic {
         use klib::fmt::FormattedWriter;
         $crate::arch::interrupt::disable_interrupt();
         klib::make_args!($($args),*).format(&mut $crate::console::ConsoleWriter, $fmt);
+        $crate::diag::dump_state();
         loop {}
     }
 }
+
+use crate::arch;
+use crate::symbols::Symbolized;
+use crate::task::{self, TaskOps};
+
+static mut DUMPING: bool = false;
+
+// Prints the current task, its trap frame and a backtrace of the kernel stack.
+pub fn dump_state() {
+    unsafe {
+        // Don't recurse if we panic while dumping.
+        if DUMPING {
+            return;
+        }
+        DUMPING = true;
+    }
+
+    let current = task::get_task_pool().current();
+    printk!(b"current task: {}\n", current.tid());
+    printk!(b"trap frame:\n");
+    arch::diag::dump_trap_frame(current);
+    printk!(b"backtrace:\n");
+    arch::diag::backtrace(&mut |ra| {
+        printk!(b"    {} {}\n", ra as usize, Symbolized(ra));
+    });
+}
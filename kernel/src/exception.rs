@@ -0,0 +1,59 @@
+use crate::ipc;
+use crate::kpanic;
+use crate::printk;
+use crate::symbols::Symbolized;
+use crate::task::{self, TaskOps, TaskType, KERNEL_TID};
+use klib::ipc::{ExceptionPayload, IpcFlags, Message, MessageType};
+
+// Handles a CPU exception caused by the current task: sends an exception
+// message to the pager of the task and suspends the task. The pager is
+// responsible for destroying (and restarting) it. An exception in the kernel
+// itself (`by_task` is false) is fatal.
+pub fn handle_exception(cause: u32, tval: u32, pc: u32, by_task: bool) {
+    if !by_task {
+        kpanic!(
+            b"exception in the kernel: cause={}, tval={}, pc={}\n",
+            cause,
+            tval as usize,
+            pc as usize
+        );
+    }
+
+    let task_pool = task::get_task_pool();
+    let current = task_pool.current();
+    printk!(
+        b"exception in task {}: cause={}, tval={}, pc={} ({})\n",
+        current.tid(),
+        cause,
+        tval as usize,
+        pc as usize,
+        Symbolized(pc)
+    );
+    if current.task_type() == TaskType::Idle || current.pager() == KERNEL_TID {
+        kpanic!(b"no pager to handle the exception\n");
+    }
+
+    let mut message = Message {
+        message_type: MessageType::EXCEPTION,
+        src_tid: KERNEL_TID,
+        ..Message::zeroed()
+    };
+    message.set_payload(&ExceptionPayload {
+        tid: current.tid(),
+        cause,
+        tval,
+        pc,
+    });
+    let r = task_pool
+        .lookup_task(current.pager())
+        .and_then(|pager| ipc::send(task_pool, pager, &message, IpcFlags::kernel(), 0));
+    if r.is_err() {
+        printk!(
+            b"task {}: failed to send an exception message to the pager: {}\n",
+            current.tid(),
+            r.err_as_u32()
+        );
+    }
+
+    ipc::suspend_current(task_pool);
+}
@@ -1,7 +1,9 @@
-use crate::task::{NotificationMessage, TaskOps, TaskPool, TaskRef, TaskState};
+use crate::task::{
+    NotificationMessage, TaskOps, TaskPool, TaskRef, TaskState, KERNEL_TID, NOT_SENDING,
+};
+use core::ptr;
 use core::u32;
 use klib::ipc::{IpcFlags, Message, Notifications};
-use klib::list::RemovableLinkedStackOps;
 use klib::result::KResult;
 
 pub struct IpcSrcTask;
@@ -11,53 +13,293 @@ impl IpcSrcTask {
     const DENY: u32 = u32::MAX;
 }
 
+/// Sends a message. If `timeout` is not zero, gives up waiting for the receiver
+/// after `timeout` ticks and returns `KResult::TryAgain`. If the out-of-line
+/// payload doesn't fit in the buffer of the receiver, returns
+/// `KResult::TooLarge` (see `rejected_ool_capacity`).
 pub fn send(
     task_pool: &TaskPool,
     dst_task: TaskRef,
     message: &Message,
     flags: IpcFlags,
+    timeout: u32,
 ) -> KResult<()> {
-    let receiver_is_ready = dst_task.state() == TaskState::Blocked
-        && (dst_task.src_tid() == IpcSrcTask::ANY
-            || dst_task.src_tid() == task_pool.current().tid());
-    if !receiver_is_ready {
+    let src_tid = if flags.is_kernel() {
+        KERNEL_TID
+    } else {
+        task_pool.current().tid()
+    };
+    if !is_receiver_ready(task_pool, dst_task) {
         if flags.is_noblock() {
             return KResult::WouldBlock;
         }
+        if wait_for_receiver(task_pool, dst_task, message, src_tid, timeout)? {
+            return KResult::Ok(());
+        }
+    }
+    check_ool_fits(task_pool, dst_task, message)?;
+    deliver(task_pool, dst_task, message, src_tid)?;
+    let current = task_pool.current();
+    if !flags.is_kernel()
+        && dst_task.priority() <= current.priority()
+        && !task_pool.has_higher_priority_task(dst_task)
+    {
+        // Fast path: the receiver would preempt us or run next anyway.
+        task_pool.switch_to(dst_task);
+    } else {
+        task_pool.resume_task(dst_task);
+        if !flags.is_kernel() && task_pool.has_higher_priority_task(current) {
+            // E.g. a server back to its base priority after the reply.
+            task_pool.task_switch();
+        }
+    }
 
-        let current = task_pool.current();
-        task_pool.set_src_tid(current, IpcSrcTask::DENY);
-        task_pool.block_task(current);
-        task_pool.append_sender(dst_task, current);
+    KResult::Ok(())
+}
+
+/// Sends a message and waits for the reply from `dst_task` in a single
+/// operation: the caller is never runnable in between, so nothing but the
+/// reply can be received. If the server can run next, switches to it directly
+/// instead of going through the runqueues. The reply can't carry an
+/// out-of-line payload.
+pub fn call(
+    task_pool: &TaskPool,
+    dst_task: TaskRef,
+    message: &mut Message,
+    timeout: u32,
+) -> KResult<()> {
+    let current = task_pool.current();
+    task_pool.set_in_call(current, true);
+    let result = send_and_wait_for_reply(task_pool, dst_task, message, timeout);
+    task_pool.set_in_call(current, false);
+    result
+}
+
+fn send_and_wait_for_reply(
+    task_pool: &TaskPool,
+    dst_task: TaskRef,
+    message: &mut Message,
+    timeout: u32,
+) -> KResult<()> {
+    let current = task_pool.current();
+    if !is_receiver_ready(task_pool, dst_task)
+        && wait_for_receiver(task_pool, dst_task, message, current.tid(), timeout)?
+    {
+        // We have also waited for the reply.
+        task_pool.update_message(current, |current_message| *message = *current_message);
+        return KResult::Ok(());
+    }
+    check_ool_fits(task_pool, dst_task, message)?;
+    deliver(task_pool, dst_task, message, current.tid())?;
+    task_pool.set_ool_buffer(current, 0, 0);
+    task_pool.set_src_tid(current, dst_task.tid());
+    task_pool.block_task(current);
+    task_pool.set_ipc_timeout(current, timeout);
+    if task_pool.has_higher_priority_task(dst_task) {
+        task_pool.resume_task(dst_task);
+        task_pool.task_switch();
+    } else {
+        task_pool.switch_to(dst_task);
+    }
+
+    wake_up_result(task_pool)?;
+    let current = task_pool.current();
+    task_pool.update_message(current, |current_message| *message = *current_message);
+    KResult::Ok(())
+}
+
+fn is_receiver_ready(task_pool: &TaskPool, dst_task: TaskRef) -> bool {
+    dst_task.state() == TaskState::Blocked
+        && (dst_task.src_tid() == IpcSrcTask::ANY
+            || dst_task.src_tid() == task_pool.current().tid())
+}
+
+/// Blocks the current task in the sender queue of `dst_task` until the
+/// receiver resumes it. Returns `true` if the receiver has taken the message
+/// from the queue instead (see `take_queued_message`): it must not be delivered
+/// again, and a caller in `call` has already got the reply.
+fn wait_for_receiver(
+    task_pool: &TaskPool,
+    dst_task: TaskRef,
+    message: &Message,
+    src_tid: u32,
+    timeout: u32,
+) -> KResult<bool> {
+    let current = task_pool.current();
+    task_pool.update_message(current, |queued| {
+        *queued = *message;
+        queued.src_tid = src_tid;
+    });
+    task_pool.set_ool_buffer(current, message.ool_ptr, message.ool_len);
+    block_in_senders(task_pool, dst_task);
+    task_pool.set_ipc_timeout(current, timeout);
+    let deadline = current.ipc_deadline();
+    loop {
         task_pool.task_switch();
 
-        let current = task_pool.current();
-        if current.notifications().is_aborted() {
-            task_pool.update_notifications(current, |n| n.clear(Notifications::aborted()));
+        let taken = current.message_taken();
+        task_pool.set_message_taken(current, false);
+        wake_up_result(task_pool)?;
+        if taken {
+            return KResult::Ok(true);
+        }
+        if dst_task.state() == TaskState::Unused {
+            // The receiver has been destroyed after it resumed us.
             return KResult::Aborted;
         }
+        if is_receiver_ready(task_pool, dst_task) {
+            return KResult::Ok(false);
+        }
+        // The receiver has resumed us but stopped waiting before we ran, e.g.
+        // its receive has timed out or been aborted: wait for it again until
+        // our own deadline.
+        block_in_senders(task_pool, dst_task);
+        task_pool.set_ipc_deadline(current, deadline);
+    }
+}
+
+fn block_in_senders(task_pool: &TaskPool, dst_task: TaskRef) {
+    let current = task_pool.current();
+    task_pool.set_src_tid(current, IpcSrcTask::DENY);
+    task_pool.append_sender(dst_task, current);
+    task_pool.block_task(current);
+}
+
+/// Returns how the blocking IPC operation of the current task has ended.
+fn wake_up_result(task_pool: &TaskPool) -> KResult<()> {
+    let current = task_pool.current();
+    if current.notifications().is_aborted() {
+        task_pool.update_notifications(current, |n| n.clear(Notifications::aborted()));
+        return KResult::Aborted;
+    }
+    if current.ipc_timed_out() {
+        task_pool.set_ipc_timed_out(current, false);
+        return KResult::TryAgain;
+    }
+    if current.ool_too_large() {
+        task_pool.set_ool_too_large(current, false);
+        return KResult::TooLarge;
+    }
+    KResult::Ok(())
+}
+
+/// Copies a message and its out-of-line payload to `dst_task` waiting for it.
+/// The caller is responsible for making `dst_task` runnable.
+fn deliver(
+    task_pool: &TaskPool,
+    dst_task: TaskRef,
+    message: &Message,
+    src_tid: u32,
+) -> KResult<()> {
+    let (buffer, capacity) = dst_task.ool_buffer();
+    if message.ool_len > capacity {
+        return KResult::TooLarge;
+    }
+    if message.ool_len > 0 {
+        // Both buffers have been validated by the syscall handlers.
+        unsafe {
+            ptr::copy_nonoverlapping(
+                message.ool_ptr as *const u8,
+                buffer as *mut u8,
+                message.ool_len,
+            );
+        }
     }
     task_pool.update_message(dst_task, |dst_msg| {
         *dst_msg = *message;
-        dst_msg.src_tid = task_pool.current().tid();
+        dst_msg.src_tid = src_tid;
+        dst_msg.ool_ptr = buffer;
     });
-    task_pool.resume_task(dst_task);
+    KResult::Ok(())
+}
+
+/// Replies to `dst_task` waiting for a message from the current task in the
+/// receive phase of `ipc_call`. Never blocks: returns `KResult::NotReady` if
+/// `dst_task` is not waiting for our reply, e.g. in a plain `ipc_recv`.
+pub fn reply(task_pool: &TaskPool, dst_task: TaskRef, message: &Message) -> KResult<()> {
+    if !dst_task.in_call()
+        || dst_task.state() != TaskState::Blocked
+        || dst_task.src_tid() != task_pool.current().tid()
+    {
+        return KResult::NotReady;
+    }
+    send(task_pool, dst_task, message, IpcFlags::noblock(), 0)
+}
+
+fn find_sender(task_pool: &TaskPool, receiver: TaskRef, src_tid: u32) -> Option<TaskRef> {
+    task_pool
+        .list_for_senders(receiver)
+        .iter()
+        .find(|sender| src_tid == IpcSrcTask::ANY || src_tid == sender.tid())
+}
+
+/// Fails the queued senders whose out-of-line payload doesn't fit in the buffer
+/// of `receiver` with `KResult::TooLarge`.
+fn reject_too_large_senders(task_pool: &TaskPool, receiver: TaskRef, src_tid: u32) {
+    let (_, capacity) = receiver.ool_buffer();
+    let too_large = |sender: &TaskRef| sender.ool_buffer().1 > capacity;
+    while let Some(sender) = find_sender(task_pool, receiver, src_tid).filter(too_large) {
+        task_pool.remove_sender(receiver, sender);
+        set_rejected_ool_capacity(task_pool, sender, capacity);
+        task_pool.set_ool_too_large(sender, true);
+        task_pool.resume_task(sender);
+    }
+}
 
+/// Fails the current task sending `message` to `dst_task` waiting for it with
+/// `KResult::TooLarge` if the out-of-line payload doesn't fit in its buffer.
+fn check_ool_fits(task_pool: &TaskPool, dst_task: TaskRef, message: &Message) -> KResult<()> {
+    let (_, capacity) = dst_task.ool_buffer();
+    if message.ool_len > capacity {
+        set_rejected_ool_capacity(task_pool, task_pool.current(), capacity);
+        return KResult::TooLarge;
+    }
+    KResult::Ok(())
+}
+
+/// Keeps the capacity of the buffer of the receiver which has rejected the
+/// out-of-line payload of `sender` in the message of `sender`, which is no
+/// longer in use.
+fn set_rejected_ool_capacity(task_pool: &TaskPool, sender: TaskRef, capacity: usize) {
+    task_pool.update_message(sender, |message| message.ool_len = capacity);
+}
+
+/// Returns the capacity of the buffer of the receiver after `send` or `call`
+/// has failed with `KResult::TooLarge`, so that the sender can retry with a
+/// payload which fits.
+pub fn rejected_ool_capacity(task_pool: &TaskPool) -> usize {
+    let mut capacity = 0;
+    task_pool.update_message(task_pool.current(), |message| capacity = message.ool_len);
+    capacity
+}
+
+/// Delivers the message of `sender` queued for `receiver` without blocking the
+/// receiver. A caller in `call` goes on to wait for the reply without running.
+fn take_queued_message(task_pool: &TaskPool, receiver: TaskRef, sender: TaskRef) -> KResult<()> {
+    let mut queued = Message::zeroed();
+    task_pool.update_message(sender, |message| queued = *message);
+    deliver(task_pool, receiver, &queued, queued.src_tid)?;
+    task_pool.remove_sender(receiver, sender);
+    task_pool.set_message_taken(sender, true);
+    if sender.in_call() {
+        task_pool.set_ool_buffer(sender, 0, 0);
+        task_pool.set_src_tid(sender, receiver.tid());
+        // Still blocked: inherits the priority to the receiver.
+        task_pool.block_task(sender);
+    } else {
+        task_pool.resume_task(sender);
+    }
     KResult::Ok(())
 }
 
 /// Resumes a sender task for the `receiver` tasks and updates `receiver->src`
 /// properly.
 fn resume_sender(task_pool: &TaskPool, receiver: TaskRef, src_tid: u32) {
-    if let Some(sender) = task_pool
-        .list_for_senders(receiver)
-        .iter()
-        .find(|sender| src_tid == IpcSrcTask::ANY || src_tid == sender.tid())
-    {
+    if let Some(sender) = find_sender(task_pool, receiver, src_tid) {
         // DEBUG_ASSERT(sender->state == TASK_BLOCKED);
         // DEBUG_ASSERT(sender->src == IPC_DENY);
         task_pool.resume_task(sender);
-        task_pool.list_for_senders(receiver).remove(sender);
+        task_pool.remove_sender(receiver, sender);
         // If src == IPC_ANY, allow only `sender` to send a message. Let's
         // consider the following situation to understand why:
         //
@@ -82,26 +324,39 @@ fn resume_sender(task_pool: &TaskPool, receiver: TaskRef, src_tid: u32) {
     }
 }
 
+/// Receives a message. If `timeout` is not zero, gives up waiting after `timeout`
+/// ticks and returns `KResult::TryAgain`.
 pub fn recv(
     task_pool: &TaskPool,
     src_tid: u32,
     message: &mut Message,
     flags: IpcFlags,
+    timeout: u32,
 ) -> KResult<()> {
     if src_tid == IpcSrcTask::ANY && task_pool.current().notifications().exists() {
         let current = task_pool.current();
-        message.set_notification(current.notifications());
+        message.set_notification(
+            current.notifications(),
+            task_pool.take_fired_timers(current),
+        );
         task_pool.update_notifications(current, |_| Notifications::none());
     } else {
+        let current = task_pool.current();
+        task_pool.set_ool_buffer(current, message.ool_ptr, message.ool_len);
+        reject_too_large_senders(task_pool, current, src_tid);
         if flags.is_noblock() {
-            return KResult::WouldBlock;
+            match find_sender(task_pool, current, src_tid) {
+                Some(sender) => take_queued_message(task_pool, current, sender)?,
+                None => return KResult::WouldBlock,
+            }
+        } else {
+            resume_sender(task_pool, current, src_tid);
+            task_pool.block_task(current);
+            task_pool.set_ipc_timeout(current, timeout);
+            task_pool.task_switch();
+            wake_up_result(task_pool)?;
         }
 
-        let current = task_pool.current();
-        resume_sender(task_pool, current, src_tid);
-        task_pool.block_task(current);
-        task_pool.task_switch();
-
         let current = task_pool.current();
         task_pool.update_message(current, |current_message| *message = *current_message);
     }
@@ -118,7 +373,7 @@ pub fn notify(
         // Send a NOTIFICATIONS message immediately.
         let dst_notifications = dst_task.notifications() | notifications;
         task_pool.update_message(dst_task, |dst_msg| {
-            dst_msg.set_notification(dst_notifications)
+            dst_msg.set_notification(dst_notifications, task_pool.take_fired_timers(dst_task))
         });
         task_pool.update_notifications(dst_task, |_| Notifications::none());
         task_pool.resume_task(dst_task);
@@ -129,3 +384,46 @@ pub fn notify(
     }
     KResult::Ok(())
 }
+
+/// Removes a blocked task from the sender queue of its receiver, if any.
+fn cancel_send(task_pool: &TaskPool, task: TaskRef) {
+    if task.dst_tid() != NOT_SENDING {
+        if let KResult::Ok(receiver) = task_pool.lookup_task(task.dst_tid()) {
+            task_pool.remove_sender(receiver, task);
+        }
+    }
+}
+
+/// Wakes up a task whose blocking IPC operation has timed out.
+pub fn time_out(task_pool: &TaskPool, task: TaskRef) {
+    if task.state() != TaskState::Blocked {
+        return;
+    }
+    cancel_send(task_pool, task);
+    task_pool.set_ipc_timed_out(task, true);
+    task_pool.resume_task(task);
+}
+
+/// Cancels the pending IPC operation of `task`. The operation returns
+/// `KResult::Aborted`. A sender which `task` has resumed but which hasn't run
+/// yet waits for `task` again (see `wait_for_receiver`).
+pub fn abort(task_pool: &TaskPool, task: TaskRef) -> KResult<()> {
+    if task.state() != TaskState::Blocked {
+        return KResult::NotReady;
+    }
+    cancel_send(task_pool, task);
+    task_pool.abort_ipc(task);
+    KResult::Ok(())
+}
+
+/// Blocks the current task forever: nobody can send a message to it and
+/// `abort` doesn't wake it up. The task is released only when destroyed.
+pub fn suspend_current(task_pool: &TaskPool) -> ! {
+    loop {
+        let current = task_pool.current();
+        task_pool.set_src_tid(current, IpcSrcTask::DENY);
+        task_pool.block_task(current);
+        task_pool.task_switch();
+        task_pool.update_notifications(current, |n| n.clear(Notifications::aborted()));
+    }
+}
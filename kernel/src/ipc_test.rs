@@ -0,0 +1,726 @@
+use crate::arch::mock::task::{run_kernel, spawn, switches};
+use crate::arch::mock::timer;
+use crate::ipc;
+use crate::task::{self, TaskOps, TaskRef, TaskState};
+use klib::ipc::{IpcFlags, Message, MessageType, NotificationPayload, Notifications};
+use klib::result::KResult;
+use std::sync::mpsc;
+
+const TEST: MessageType = MessageType(100);
+
+fn message(value: u8) -> Message {
+    let mut message = Message {
+        message_type: TEST,
+        ..Message::zeroed()
+    };
+    message.raw[0] = value;
+    message
+}
+
+fn lookup(tid: u32) -> TaskRef {
+    task::get_task_pool().lookup_task(tid).ok().unwrap()
+}
+
+fn send(dst_tid: u32, value: u8) -> KResult<()> {
+    let task_pool = task::get_task_pool();
+    ipc::send(
+        task_pool,
+        lookup(dst_tid),
+        &message(value),
+        IpcFlags::block(),
+        0,
+    )
+}
+
+fn recv(src_tid: u32) -> KResult<Message> {
+    let mut message = message(0);
+    ipc::recv(
+        task::get_task_pool(),
+        src_tid,
+        &mut message,
+        IpcFlags::block(),
+        0,
+    )
+    .map(|_| message)
+}
+
+// Runs the tasks until all of them are blocked or exited.
+fn run_tasks() {
+    task::get_task_pool().task_switch();
+}
+
+#[test]
+fn ipc_send_to_waiting_receiver() {
+    let (tx, rx) = mpsc::channel();
+    run_kernel(|| {
+        spawn(1, move || {
+            let message = recv(0).ok().unwrap();
+            tx.send((message.src_tid, message.raw[0])).unwrap();
+        });
+        spawn(2, || assert!(send(1, 42).is_ok()));
+        run_tasks();
+        assert_eq!(rx.recv().unwrap(), (2, 42));
+        // The receiver runs right away in the sender's quantum.
+        assert_eq!(switches(), [(0, 1), (1, 2), (2, 1), (1, 2), (2, 0)]);
+    });
+}
+
+#[test]
+fn ipc_send_donates_quantum() {
+    let (tx, rx) = mpsc::channel();
+    run_kernel(|| {
+        spawn(1, move || {
+            assert!(recv(0).is_ok());
+            tx.send(lookup(1).time_slice() - lookup(1).quantum())
+                .unwrap();
+        });
+        spawn(2, || {
+            task::handle_timer_irq();
+            assert!(send(1, 0).is_ok());
+        });
+        run_tasks();
+        // The sender has consumed a tick.
+        assert_eq!(rx.recv().unwrap(), 1);
+    });
+}
+
+#[test]
+fn ipc_send_to_lower_priority_receiver() {
+    run_kernel(|| {
+        spawn(1, || assert!(recv(0).is_ok()));
+        spawn(2, || {
+            let task_pool = task::get_task_pool();
+            assert!(task_pool.schedule_task(lookup(2), 0, 0).is_ok());
+            assert!(send(1, 0).is_ok());
+            // The receiver waits in the runqueue.
+            assert!(lookup(1).state() == TaskState::Runnable);
+            assert_eq!(switches().len(), 2);
+        });
+        run_tasks();
+        assert_eq!(switches(), [(0, 1), (1, 2), (2, 1), (1, 0)]);
+    });
+}
+
+#[test]
+fn ipc_senders_are_served_in_order() {
+    let (tx, rx) = mpsc::channel();
+    run_kernel(|| {
+        // The senders run first and get queued.
+        spawn(2, || assert!(send(1, 2).is_ok()));
+        spawn(3, || assert!(send(1, 3).is_ok()));
+        spawn(4, || assert!(send(1, 4).is_ok()));
+        spawn(1, move || {
+            for _ in 0..3 {
+                let message = recv(0).ok().unwrap();
+                assert_eq!(message.src_tid as u8, message.raw[0]);
+                tx.send(message.src_tid).unwrap();
+            }
+        });
+        run_tasks();
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), [2, 3, 4]);
+    });
+}
+
+#[test]
+fn ipc_recv_from_specific_sender() {
+    let (tx, rx) = mpsc::channel();
+    run_kernel(|| {
+        spawn(2, || assert!(send(1, 2).is_ok()));
+        spawn(3, || assert!(send(1, 3).is_ok()));
+        spawn(1, move || {
+            tx.send(recv(3).ok().unwrap().src_tid).unwrap();
+            tx.send(recv(2).ok().unwrap().src_tid).unwrap();
+        });
+        run_tasks();
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), [3, 2]);
+    });
+}
+
+// The race described in `resume_sender`: the receiver C resumes the queued
+// sender A, and then B tries to send before A runs. B must wait for A.
+#[test]
+fn ipc_resumed_sender_goes_first() {
+    const C: u32 = 1;
+    const A: u32 = 2;
+    const B: u32 = 3;
+    let (tx, rx) = mpsc::channel();
+    run_kernel(|| {
+        // A gets queued since C is not receiving yet.
+        spawn(A, || assert!(send(C, A as u8).is_ok()));
+        // C resumes A and waits for its message. A is queued after B in the
+        // runqueue.
+        spawn(C, move || {
+            for _ in 0..2 {
+                tx.send(recv(0).ok().unwrap().src_tid).unwrap();
+            }
+        });
+        // B runs before A and must not be accepted.
+        spawn(B, || {
+            assert_eq!(lookup(C).src_tid(), A);
+            assert!(send(C, B as u8).is_ok());
+        });
+        run_tasks();
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), [A, B]);
+        assert_eq!(
+            &switches()[..4],
+            [(0, A), (A, C), (C, B), (B, A)],
+            "B should be blocked until A delivers the message"
+        );
+    });
+}
+
+fn call(dst_tid: u32, value: u8) -> KResult<Message> {
+    let mut message = message(value);
+    ipc::call(task::get_task_pool(), lookup(dst_tid), &mut message, 0).map(|_| message)
+}
+
+// Replies to each message with its value incremented.
+fn echo_server() {
+    loop {
+        let request = recv(0).ok().unwrap();
+        let task_pool = task::get_task_pool();
+        let reply = message(request.raw[0] + 1);
+        assert!(ipc::reply(task_pool, lookup(request.src_tid), &reply).is_ok());
+    }
+}
+
+#[test]
+fn ipc_call_switches_to_server_directly() {
+    let (tx, rx) = mpsc::channel();
+    run_kernel(|| {
+        spawn(1, echo_server);
+        spawn(2, move || {
+            let reply = call(1, 41).ok().unwrap();
+            tx.send((reply.src_tid, reply.raw[0])).unwrap();
+        });
+        spawn(3, || {});
+        run_tasks();
+        assert_eq!(rx.recv().unwrap(), (1, 42));
+        // The server, and the caller on the reply, run before the task 3
+        // queued earlier.
+        assert_eq!(
+            switches(),
+            [(0, 1), (1, 2), (2, 1), (1, 2), (2, 3), (3, 1), (1, 0)]
+        );
+    });
+}
+
+#[test]
+fn ipc_call_waits_only_for_reply() {
+    let (tx, rx) = mpsc::channel();
+    run_kernel(|| {
+        spawn(1, || {
+            let request = recv(0).ok().unwrap();
+            let task_pool = task::get_task_pool();
+            assert!(ipc::notify(task_pool, lookup(2), Notifications::irq()).is_ok());
+            // Let the task 3 try sending to the caller.
+            assert!(recv(3).is_ok());
+            assert!(ipc::reply(task_pool, lookup(request.src_tid), &message(7)).is_ok());
+        });
+        let caller_tx = tx.clone();
+        spawn(2, move || {
+            caller_tx
+                .send(call(1, 0).ok().unwrap().raw[0] as u32)
+                .unwrap();
+            let payload = NotificationPayload::parse(&recv(0).ok().unwrap()).unwrap();
+            caller_tx.send(payload.notifications.as_u32()).unwrap();
+        });
+        spawn(3, move || {
+            let task_pool = task::get_task_pool();
+            let result = ipc::send(task_pool, lookup(2), &message(0), IpcFlags::noblock(), 0);
+            tx.send(matches!(result, KResult::WouldBlock) as u32)
+                .unwrap();
+            assert!(send(1, 0).is_ok());
+        });
+        run_tasks();
+        // Neither the other sender nor the notification gets in before the reply.
+        assert_eq!(rx.recv().unwrap(), 1);
+        assert_eq!(rx.recv().unwrap(), 7);
+        assert!(Notifications::from_u32(rx.recv().unwrap()).is_irq());
+    });
+}
+
+#[test]
+fn ipc_server_inherits_caller_priority() {
+    const SERVER: u32 = 1;
+    const CLIENT: u32 = 2;
+    const OTHER: u32 = 3;
+    let (tx, rx) = mpsc::channel();
+    run_kernel(|| {
+        let server_tx = tx.clone();
+        spawn(SERVER, move || {
+            // Runs first as the client is queued in our senders.
+            server_tx.send(lookup(SERVER).priority()).unwrap();
+            let request = recv(0).ok().unwrap();
+            server_tx.send(lookup(SERVER).priority()).unwrap();
+            // The task in the middle doesn't preempt us.
+            task::handle_timer_irq();
+            assert!(lookup(OTHER).state() == TaskState::Runnable);
+            let task_pool = task::get_task_pool();
+            assert!(ipc::reply(task_pool, lookup(request.src_tid), &message(0)).is_ok());
+        });
+        spawn(CLIENT, move || {
+            assert!(call(SERVER, 0).is_ok());
+            tx.send(lookup(SERVER).priority()).unwrap();
+        });
+        spawn(OTHER, || {});
+        let task_pool = task::get_task_pool();
+        assert!(task_pool.schedule_task(lookup(CLIENT), 0, 0).is_ok());
+        assert!(task_pool.schedule_task(lookup(OTHER), 3, 0).is_ok());
+        run_tasks();
+        // Back to the base priority after the reply.
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), [0, 0, 7]);
+        // The task in the middle runs only after the client is done.
+        assert_eq!(
+            switches(),
+            [
+                (0, CLIENT),
+                (CLIENT, SERVER),
+                (SERVER, CLIENT),
+                (CLIENT, SERVER),
+                (SERVER, CLIENT),
+                (CLIENT, OTHER),
+                (OTHER, SERVER),
+                (SERVER, 0)
+            ]
+        );
+    });
+}
+
+#[test]
+fn ipc_priority_inheritance_is_transitive() {
+    let (tx, rx) = mpsc::channel();
+    run_kernel(|| {
+        // The task 3 calls 2, which calls 1.
+        spawn(1, move || {
+            let request = recv(0).ok().unwrap();
+            tx.send(lookup(1).priority()).unwrap();
+            let task_pool = task::get_task_pool();
+            assert!(ipc::reply(task_pool, lookup(request.src_tid), &message(0)).is_ok());
+            tx.send(lookup(1).priority()).unwrap();
+        });
+        spawn(2, || {
+            let request = recv(0).ok().unwrap();
+            assert!(call(1, 0).is_ok());
+            let task_pool = task::get_task_pool();
+            assert!(ipc::reply(task_pool, lookup(request.src_tid), &message(0)).is_ok());
+        });
+        spawn(3, || assert!(call(2, 0).is_ok()));
+        let task_pool = task::get_task_pool();
+        assert!(task_pool.schedule_task(lookup(1), 6, 0).is_ok());
+        assert!(task_pool.schedule_task(lookup(2), 5, 0).is_ok());
+        assert!(task_pool.schedule_task(lookup(3), 1, 0).is_ok());
+        run_tasks();
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), [1, 6]);
+    });
+}
+
+#[test]
+fn ipc_recv_from_specific_task_lends_no_priority() {
+    let (tx, rx) = mpsc::channel();
+    run_kernel(|| {
+        spawn(1, || assert!(recv(2).is_ok()));
+        spawn(2, move || {
+            // Not boosted by the receiver waiting for us.
+            tx.send(lookup(2).priority()).unwrap();
+            assert!(send(1, 0).is_ok());
+        });
+        let task_pool = task::get_task_pool();
+        assert!(task_pool.schedule_task(lookup(1), 1, 0).is_ok());
+        assert!(task_pool.schedule_task(lookup(2), 5, 0).is_ok());
+        run_tasks();
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), [5]);
+    });
+}
+
+#[test]
+fn ipc_reply_yields_to_higher_priority_task() {
+    const SERVER: u32 = 1;
+    const CLIENT: u32 = 2;
+    const HIGH: u32 = 3;
+    let (tx, rx) = mpsc::channel();
+    run_kernel(|| {
+        let server_tx = tx.clone();
+        spawn(SERVER, move || {
+            let request = recv(0).ok().unwrap();
+            let task_pool = task::get_task_pool();
+            // Wakes up a task above the client but below our inherited priority.
+            assert!(ipc::notify(task_pool, lookup(HIGH), Notifications::irq()).is_ok());
+            assert!(ipc::reply(task_pool, lookup(request.src_tid), &message(0)).is_ok());
+            server_tx.send(SERVER).unwrap();
+        });
+        let client_tx = tx.clone();
+        spawn(CLIENT, move || {
+            assert!(call(SERVER, 0).is_ok());
+            client_tx.send(CLIENT).unwrap();
+        });
+        spawn(HIGH, move || {
+            assert!(recv(0).is_ok());
+            tx.send(HIGH).unwrap();
+        });
+        let task_pool = task::get_task_pool();
+        assert!(task_pool.schedule_task(lookup(SERVER), 5, 0).is_ok());
+        assert!(task_pool.schedule_task(lookup(CLIENT), 2, 0).is_ok());
+        assert!(task_pool.schedule_task(lookup(HIGH), 1, 0).is_ok());
+        run_tasks();
+        // The server, back to its base priority, runs last.
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), [HIGH, CLIENT, SERVER]);
+    });
+}
+
+#[test]
+fn ipc_reply_to_caller() {
+    let (tx, rx) = mpsc::channel();
+    run_kernel(|| {
+        spawn(3, || {
+            let _ = recv(0);
+        });
+        spawn(4, || {
+            let _ = recv(1);
+        });
+        spawn(1, move || {
+            let request = recv(0).ok().unwrap();
+            let task_pool = task::get_task_pool();
+            // The task 3 is not waiting for us, and the task 4 is waiting for
+            // a message from us but not in `ipc::call`: fails without blocking.
+            for tid in [3, 4] {
+                assert!(matches!(
+                    ipc::reply(task_pool, lookup(tid), &message(0)),
+                    KResult::NotReady
+                ));
+            }
+            assert!(ipc::reply(task_pool, lookup(request.src_tid), &message(43)).is_ok());
+        });
+        spawn(2, move || {
+            tx.send(call(1, 42).ok().unwrap().raw[0]).unwrap();
+        });
+        run_tasks();
+        assert_eq!(rx.recv().unwrap(), 43);
+    });
+}
+
+fn send_ool(dst_tid: u32, data: &[u8]) -> KResult<()> {
+    let mut message = message(0);
+    message.set_ool(data);
+    ipc::send(
+        task::get_task_pool(),
+        lookup(dst_tid),
+        &message,
+        IpcFlags::block(),
+        0,
+    )
+}
+
+// Receives a message and returns its out-of-line payload.
+fn recv_ool(buffer: &mut [u8]) -> KResult<Vec<u8>> {
+    let mut message = message(0);
+    message.ool_ptr = buffer.as_mut_ptr() as usize;
+    message.ool_len = buffer.len();
+    ipc::recv(task::get_task_pool(), 0, &mut message, IpcFlags::block(), 0)?;
+    assert_eq!(message.ool_ptr, buffer.as_ptr() as usize);
+    KResult::Ok(buffer[..message.ool_len].to_vec())
+}
+
+#[test]
+fn ipc_ool_payload_is_copied() {
+    let (tx, rx) = mpsc::channel();
+    run_kernel(|| {
+        // Queued since the receiver is not receiving yet.
+        spawn(2, || assert!(send_ool(1, b"queued").is_ok()));
+        spawn(1, move || {
+            let mut buffer = [0; 16];
+            for _ in 0..2 {
+                tx.send(recv_ool(&mut buffer).ok().unwrap()).unwrap();
+            }
+        });
+        // Delivered to the waiting receiver.
+        spawn(3, || assert!(send_ool(1, b"direct").is_ok()));
+        run_tasks();
+        assert_eq!(
+            rx.try_iter().collect::<Vec<_>>(),
+            [b"queued".to_vec(), b"direct".to_vec()]
+        );
+    });
+}
+
+#[test]
+fn ipc_ool_payload_too_large() {
+    let (tx, rx) = mpsc::channel();
+    run_kernel(|| {
+        // Rejected when the receiver designates its buffer.
+        spawn(2, || {
+            assert!(matches!(send_ool(1, b"too large"), KResult::TooLarge));
+            assert_eq!(ipc::rejected_ool_capacity(task::get_task_pool()), 4);
+        });
+        spawn(1, move || {
+            let mut buffer = [0; 4];
+            tx.send(recv_ool(&mut buffer).ok().unwrap()).unwrap();
+        });
+        // Rejected without blocking since the receiver is waiting.
+        spawn(3, || {
+            assert!(matches!(send_ool(1, b"too large"), KResult::TooLarge));
+            assert_eq!(ipc::rejected_ool_capacity(task::get_task_pool()), 4);
+            assert!(send_ool(1, b"fits").is_ok());
+        });
+        run_tasks();
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), [b"fits".to_vec()]);
+    });
+}
+
+#[test]
+fn ipc_noblock() {
+    run_kernel(|| {
+        spawn(1, || {
+            let mut message = message(0);
+            let task_pool = task::get_task_pool();
+            assert!(matches!(
+                ipc::recv(task_pool, 0, &mut message, IpcFlags::noblock(), 0),
+                KResult::WouldBlock
+            ));
+            assert!(matches!(
+                ipc::send(task_pool, lookup(2), &message, IpcFlags::noblock(), 0),
+                KResult::WouldBlock
+            ));
+        });
+        spawn(2, || {});
+        run_tasks();
+    });
+}
+
+fn recv_noblock(src_tid: u32) -> KResult<Message> {
+    let mut message = message(0);
+    ipc::recv(
+        task::get_task_pool(),
+        src_tid,
+        &mut message,
+        IpcFlags::noblock(),
+        0,
+    )
+    .map(|_| message)
+}
+
+#[test]
+fn ipc_noblock_recv_takes_queued_message() {
+    let (tx, rx) = mpsc::channel();
+    run_kernel(|| {
+        spawn(2, || assert!(send(1, 42).is_ok()));
+        spawn(1, move || {
+            let message = recv_noblock(0).ok().unwrap();
+            tx.send((message.src_tid, message.raw[0])).unwrap();
+        });
+        run_tasks();
+        assert_eq!(rx.recv().unwrap(), (2, 42));
+        // The receiver keeps running until it exits.
+        assert_eq!(switches(), [(0, 2), (2, 1), (1, 2), (2, 0)]);
+    });
+}
+
+#[test]
+fn ipc_noblock_recv_takes_queued_call() {
+    let (tx, rx) = mpsc::channel();
+    run_kernel(|| {
+        spawn(2, move || {
+            tx.send(call(1, 42).ok().unwrap().raw[0]).unwrap();
+        });
+        spawn(1, || {
+            let request = recv_noblock(0).ok().unwrap();
+            // The caller waits for the reply right away.
+            let task_pool = task::get_task_pool();
+            let reply = message(request.raw[0] + 1);
+            assert!(ipc::reply(task_pool, lookup(request.src_tid), &reply).is_ok());
+        });
+        run_tasks();
+        assert_eq!(rx.recv().unwrap(), 43);
+    });
+}
+
+#[test]
+fn ipc_recv_timeout() {
+    let (tx, rx) = mpsc::channel();
+    run_kernel(|| {
+        spawn(1, move || {
+            let mut message = message(0);
+            let result = ipc::recv(
+                task::get_task_pool(),
+                0,
+                &mut message,
+                IpcFlags::block(),
+                10,
+            );
+            tx.send(matches!(result, KResult::TryAgain)).unwrap();
+        });
+        run_tasks();
+        assert!(lookup(1).state() == TaskState::Blocked);
+
+        timer::advance(10);
+        task::handle_timer_irq();
+        assert!(rx.recv().unwrap());
+    });
+}
+
+fn recv_timeout(src_tid: u32, timeout: u32) -> KResult<Message> {
+    let mut message = message(0);
+    ipc::recv(
+        task::get_task_pool(),
+        src_tid,
+        &mut message,
+        IpcFlags::block(),
+        timeout,
+    )
+    .map(|_| message)
+}
+
+// The receiver resumes a queued sender and then stops waiting before the
+// lower-priority sender runs, when `interrupt` runs in a higher-priority task.
+// The sender must wait for the receiver again instead of delivering to it.
+fn receiver_stops_waiting_before_resumed_sender_runs(
+    interrupt: fn(),
+    stopped: fn(&KResult<Message>) -> bool,
+) {
+    const RECEIVER: u32 = 1;
+    const SENDER: u32 = 2;
+    const OTHER: u32 = 3;
+    let (tx, rx) = mpsc::channel();
+    run_kernel(|| {
+        spawn(RECEIVER, move || {
+            // Waits for the go from OTHER while SENDER is queued.
+            assert!(recv(OTHER).is_ok());
+            let task_pool = task::get_task_pool();
+            assert!(ipc::notify(task_pool, lookup(OTHER), Notifications::irq()).is_ok());
+            assert!(stopped(&recv_timeout(0, 10)));
+            // SENDER runs while we wait for OTHER.
+            let message = recv(OTHER).ok().unwrap();
+            tx.send(message.src_tid).unwrap();
+            let message = recv(0).ok().unwrap();
+            tx.send(message.src_tid).unwrap();
+        });
+        spawn(SENDER, || assert!(send(RECEIVER, 2).is_ok()));
+        spawn(OTHER, move || {
+            assert!(recv(0).is_ok());
+            assert!(send(RECEIVER, 0).is_ok());
+            // Woken up by RECEIVER after it has resumed SENDER.
+            assert!(recv(0).is_ok());
+            interrupt();
+            assert!(recv(0).is_ok());
+            assert!(send(RECEIVER, 3).is_ok());
+        });
+        let task_pool = task::get_task_pool();
+        assert!(task_pool.schedule_task(lookup(OTHER), 1, 0).is_ok());
+        assert!(task_pool.schedule_task(lookup(RECEIVER), 2, 0).is_ok());
+        assert!(task_pool.schedule_task(lookup(SENDER), 3, 0).is_ok());
+        run_tasks();
+        assert!(lookup(SENDER).dst_tid() == RECEIVER);
+
+        assert!(ipc::notify(task_pool, lookup(OTHER), Notifications::irq()).is_ok());
+        run_tasks();
+        // SENDER is queued again.
+        assert!(lookup(SENDER).dst_tid() == RECEIVER);
+
+        assert!(ipc::notify(task_pool, lookup(OTHER), Notifications::irq()).is_ok());
+        run_tasks();
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), [OTHER, SENDER]);
+    });
+}
+
+#[test]
+fn ipc_recv_timeout_after_resuming_sender() {
+    receiver_stops_waiting_before_resumed_sender_runs(
+        || {
+            timer::advance(10);
+            task::handle_timer_irq();
+        },
+        |result| matches!(result, KResult::TryAgain),
+    );
+}
+
+#[test]
+fn ipc_abort_after_resuming_sender() {
+    receiver_stops_waiting_before_resumed_sender_runs(
+        // Aborts the receive of the receiver.
+        || assert!(ipc::abort(task::get_task_pool(), lookup(1)).is_ok()),
+        |result| matches!(result, KResult::Aborted),
+    );
+}
+
+#[test]
+fn ipc_notify_waiting_receiver() {
+    let (tx, rx) = mpsc::channel();
+    run_kernel(|| {
+        spawn(1, move || {
+            let payload = NotificationPayload::parse(&recv(0).ok().unwrap()).unwrap();
+            tx.send(payload.notifications).unwrap();
+        });
+        run_tasks();
+
+        let task_pool = task::get_task_pool();
+        assert!(ipc::notify(task_pool, lookup(1), Notifications::irq()).is_ok());
+        run_tasks();
+        let notifications = rx.recv().unwrap();
+        assert!(notifications.is_irq() && !notifications.is_timer());
+    });
+}
+
+// Notifications sent while the task is not waiting for them are coalesced
+// into a single message.
+#[test]
+fn ipc_notifications_are_coalesced() {
+    let (tx, rx) = mpsc::channel();
+    run_kernel(|| {
+        spawn(2, || assert!(send(1, 2).is_ok()));
+        spawn(1, move || {
+            // Notifications are not delivered while waiting for a specific task.
+            tx.send(recv(2).ok().unwrap().message_type == TEST).unwrap();
+            let payload = NotificationPayload::parse(&recv(0).ok().unwrap()).unwrap();
+            let notifications = payload.notifications;
+            tx.send(notifications.is_irq() && notifications.is_timer())
+                .unwrap();
+            // Nothing is pending anymore.
+            assert!(!lookup(1).notifications().exists());
+        });
+        let task_pool = task::get_task_pool();
+        assert!(ipc::notify(task_pool, lookup(1), Notifications::irq()).is_ok());
+        assert!(ipc::notify(task_pool, lookup(1), Notifications::timer()).is_ok());
+        run_tasks();
+        assert!(rx.recv().unwrap());
+        assert!(rx.recv().unwrap());
+    });
+}
+
+#[test]
+fn ipc_timer_notification() {
+    let (tx, rx) = mpsc::channel();
+    run_kernel(|| {
+        spawn(1, move || {
+            let task_pool = task::get_task_pool();
+            assert!(task_pool.set_timer(task_pool.current(), 3, 5, 0).is_ok());
+            let payload = NotificationPayload::parse(&recv(0).ok().unwrap()).unwrap();
+            tx.send((payload.notifications.is_timer(), payload.fired_timers))
+                .unwrap();
+        });
+        run_tasks();
+
+        timer::advance(4);
+        task::handle_timer_irq();
+        assert!(rx.try_recv().is_err());
+        timer::advance(1);
+        task::handle_timer_irq();
+        assert_eq!(rx.recv().unwrap(), (true, 1 << 3));
+    });
+}
+
+#[test]
+fn ipc_abort() {
+    let (tx, rx) = mpsc::channel();
+    run_kernel(|| {
+        // The task 2 doesn't exist and never replies.
+        spawn(1, move || {
+            tx.send(matches!(recv(2), KResult::Aborted)).unwrap()
+        });
+        run_tasks();
+
+        let task_pool = task::get_task_pool();
+        assert!(matches!(ipc::abort(task_pool, lookup(1)), KResult::Ok(())));
+        run_tasks();
+        assert!(rx.recv().unwrap());
+    });
+}
@@ -0,0 +1,95 @@
+use crate::arch::irq as arch;
+use crate::config;
+use crate::ipc;
+use crate::task::{self, TaskOps, TaskRef};
+use core::cell::Cell;
+use core::mem;
+use klib::ipc::Notifications;
+use klib::result::KResult;
+use klib::zeroed_array;
+
+// Maps each interrupt line to the TID of the task handling it. `KERNEL_TID` means
+// the line has no owner.
+pub struct IrqTable {
+    owners: [Cell<u32>; config::NUM_IRQS as usize],
+}
+
+static mut IRQ_TABLE: IrqTable = IrqTable {
+    owners: zeroed_array!(Cell<u32>, config::NUM_IRQS as usize),
+};
+
+impl IrqTable {
+    fn owner(&self, irq: u32) -> KResult<&Cell<u32>> {
+        if irq >= config::NUM_IRQS {
+            KResult::InvalidArg
+        } else {
+            KResult::Ok(unsafe { self.owners.get_unchecked(irq as usize) })
+        }
+    }
+
+    pub fn acquire(&self, task: TaskRef, irq: u32) -> KResult<()> {
+        let owner = self.owner(irq)?;
+        if owner.get() != task::KERNEL_TID {
+            return KResult::InUse;
+        }
+        owner.set(task.tid());
+        arch::enable_irq(irq);
+        KResult::Ok(())
+    }
+
+    pub fn release(&self, task: TaskRef, irq: u32) -> KResult<()> {
+        let owner = self.owner(irq)?;
+        if owner.get() != task.tid() {
+            return KResult::NotPermitted;
+        }
+        arch::disable_irq(irq);
+        owner.set(task::KERNEL_TID);
+        KResult::Ok(())
+    }
+
+    // Unmasks the line masked in `handle_external_irq` once the owner has handled it.
+    pub fn acknowledge(&self, task: TaskRef, irq: u32) -> KResult<()> {
+        let owner = self.owner(irq)?;
+        if owner.get() != task.tid() {
+            return KResult::NotPermitted;
+        }
+        arch::enable_irq(irq);
+        KResult::Ok(())
+    }
+
+    pub fn release_all(&self, task: TaskRef) {
+        for irq in 0..config::NUM_IRQS {
+            let _ = self.release(task, irq);
+        }
+    }
+}
+
+pub fn get_irq_table() -> &'static IrqTable {
+    unsafe { &IRQ_TABLE }
+}
+
+pub fn handle_external_irq() {
+    let task_pool = task::get_task_pool();
+    let irq_table = get_irq_table();
+
+    let mut pending = arch::pending_irqs();
+    let mut notified = false;
+    while pending != 0 {
+        let irq = pending.trailing_zeros();
+        pending &= !(1 << irq);
+
+        // Keep the line masked until the owner acknowledges it. Otherwise a
+        // level-triggered device would interrupt us again immediately.
+        arch::disable_irq(irq);
+        let owner = irq_table.owner(irq).map(|owner| owner.get());
+        if let KResult::Ok(owner) = owner.and_then(|tid| task_pool.lookup_task(tid)) {
+            if owner.tid() != task::KERNEL_TID {
+                notified |= ipc::notify(task_pool, owner, Notifications::irq()).is_ok();
+            }
+        }
+    }
+
+    if notified {
+        task_pool.task_switch();
+    }
+}
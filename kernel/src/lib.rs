@@ -1,4 +1,4 @@
-#![no_std]
+#![cfg_attr(not(test), no_std)]
 #![feature(fn_traits)]
 #![feature(unboxed_closures)]
 #![feature(try_trait_v2)]
@@ -14,14 +14,26 @@ mod boot;
 mod config;
 mod console;
 mod diag;
+mod exception;
 mod ipc;
+#[cfg(test)]
+mod ipc_test;
+mod irq;
+mod symbols;
 mod syscall;
 mod task;
+#[cfg(test)]
+mod task_test;
+mod timer;
+mod user_ptr;
 
-use core::panic::PanicInfo;
+// Only required to link: .panic_info is discarded, so the kernel must not
+// have panic paths. Fatal errors are reported through `kpanic!`. Tests use the
+// panic handler of std.
+#[cfg(not(test))]
 #[panic_handler]
 #[no_mangle]
 #[link_section = ".panic_info"]
-fn panic(_info: &PanicInfo) -> ! {
+fn panic(_info: &core::panic::PanicInfo) -> ! {
     loop {}
 }
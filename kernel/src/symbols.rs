@@ -0,0 +1,92 @@
+// Tests have no symbol table: see `symbol_table`.
+#![cfg_attr(test, allow(dead_code, unused_imports))]
+
+use core::mem;
+use core::slice;
+use klib::buf_fmt;
+use klib::fmt::{Display, Write};
+use klib::local_address_of;
+
+// The symbol table embedded into .symbols by symbol_table.rb.
+const SYMBOL_TABLE_MAGIC: [u8; 4] = *b"SYMS";
+
+#[repr(C)]
+struct SymbolTableHeader {
+    magic: [u8; 4],
+    num_symbols: u32,
+}
+
+#[repr(C)]
+struct Symbol {
+    addr: u32,
+    // The offset of the NUL-terminated name in the strings.
+    name_offset: u32,
+}
+
+#[cfg(not(test))]
+fn symbol_table() -> Option<(&'static [Symbol], &'static [u8])> {
+    let start = local_address_of!("__symbols_start");
+    let end = local_address_of!("__symbols_end");
+    let header = unsafe { (start as *const SymbolTableHeader).read() };
+    if header.magic != SYMBOL_TABLE_MAGIC {
+        // The image is built without the symbol table.
+        return None;
+    }
+
+    let symbols_start = start + mem::size_of::<SymbolTableHeader>() as u32;
+    let strings_start = symbols_start + header.num_symbols * mem::size_of::<Symbol>() as u32;
+    if strings_start > end {
+        return None;
+    }
+    unsafe {
+        Some((
+            slice::from_raw_parts(symbols_start as *const Symbol, header.num_symbols as usize),
+            slice::from_raw_parts(strings_start as *const u8, (end - strings_start) as usize),
+        ))
+    }
+}
+
+#[cfg(not(test))]
+fn text_end() -> u32 {
+    local_address_of!("__text_end")
+}
+
+// Tests are not linked with the linker script and have no symbol table.
+#[cfg(test)]
+fn symbol_table() -> Option<(&'static [Symbol], &'static [u8])> {
+    None
+}
+
+#[cfg(test)]
+fn text_end() -> u32 {
+    0
+}
+
+// Returns the name of the function containing `addr` and the offset from it.
+pub fn lookup(addr: u32) -> Option<(&'static [u8], u32)> {
+    if addr >= text_end() {
+        return None;
+    }
+    let (symbols, strings) = symbol_table()?;
+    let index = symbols
+        .partition_point(|symbol| symbol.addr <= addr)
+        .checked_sub(1)?;
+    let symbol = symbols.get(index)?;
+    let name = strings.get(symbol.name_offset as usize..)?;
+    let name_len = name.iter().position(|&ch| ch == 0).unwrap_or(name.len());
+    Some((name.get(..name_len)?, addr - symbol.addr))
+}
+
+// Formats an address as `function+offset`, or `?` if unknown.
+pub struct Symbolized(pub u32);
+
+impl Display for Symbolized {
+    fn fmt(&self, writer: &mut dyn Write) {
+        match lookup(self.0) {
+            Some((name, offset)) => {
+                buf_fmt!(writer, b"{}+{}", name, offset as usize);
+            }
+            None => writer.write_char(b'?'),
+        }
+    }
+}
@@ -1,16 +1,42 @@
 use crate::config;
 use crate::console::Console;
 use crate::ipc;
-use crate::task;
-use core::mem;
-use core::slice;
+use crate::irq;
+use crate::task::{self, TaskInfo, TaskOps, TaskPool, TaskType};
+use crate::timer;
+use crate::user_ptr::{UserPtr, UserSlice};
 use klib::ipc::{IpcFlags, Message, Notifications};
 use klib::result::KResult;
 use klib::syscall::Syscall;
 
+// Arms the timer 0. Kept for tasks which need only one timer.
 fn handle_set_timer(timeout: u32) -> KResult<()> {
     let task_pool = task::get_task_pool();
-    task_pool.set_current_timeout(timeout)
+    task_pool.set_timer(task_pool.current(), 0, timeout, 0)
+}
+
+fn handle_timer_set(id: u32, ms: u32, period: u32) -> KResult<()> {
+    if ms == 0 {
+        return KResult::InvalidArg;
+    }
+    let task_pool = task::get_task_pool();
+    task_pool.set_timer(task_pool.current(), id, ms, period)
+}
+
+fn handle_timer_set_until(id: u32, deadline_us: u64) -> KResult<()> {
+    let task_pool = task::get_task_pool();
+    task_pool.set_timer_until(task_pool.current(), id, timer::us_to_mtime(deadline_us))
+}
+
+// Returns the time since boot in microseconds.
+fn handle_get_time(time: &mut u64) -> KResult<()> {
+    *time = timer::mtime_to_us(timer::now());
+    KResult::Ok(())
+}
+
+fn handle_timer_cancel(id: u32) -> KResult<()> {
+    let task_pool = task::get_task_pool();
+    task_pool.cancel_timer(task_pool.current(), id)
 }
 
 fn handle_console_write(s: &[u8]) -> KResult<()> {
@@ -22,28 +48,94 @@ fn handle_console_write(s: &[u8]) -> KResult<()> {
     }
 }
 
-fn handle_ipc_send(dst_tid: u32, message: &Message, flags: IpcFlags) -> KResult<()> {
+// Checks that the current task can access the out-of-line buffer of
+// `message`: read the payload to send, or write into the buffer to receive into.
+fn check_ool_buffer(message: &Message, write: bool) -> KResult<()> {
+    let current = task::get_task_pool().current();
+    let buffer = UserSlice::new(message.ool_ptr as u32, message.ool_len as u32);
+    if write {
+        buffer.as_mut_slice(current).map(|_| ())
+    } else {
+        buffer.as_slice(current).map(|_| ())
+    }
+}
+
+// On `KResult::TooLarge`, tells the sender the capacity of the buffer of the
+// receiver in `message.ool_len` so that it can retry.
+fn report_ool_capacity(
+    task_pool: &TaskPool,
+    message: &mut Message,
+    result: KResult<()>,
+) -> KResult<()> {
+    if let KResult::TooLarge = result {
+        message.ool_len = ipc::rejected_ool_capacity(task_pool);
+    }
+    result
+}
+
+fn handle_ipc_send(
+    dst_tid: u32,
+    message: &mut Message,
+    flags: IpcFlags,
+    timeout: u32,
+) -> KResult<()> {
+    check_ool_buffer(message, false)?;
+    let task_pool = task::get_task_pool();
+    let result = task_pool
+        .lookup_task(dst_tid)
+        .and_then(|task| ipc::send(task_pool, task, message, flags, timeout));
+    report_ool_capacity(task_pool, message, result)
+}
+
+// The caller waits for the reply in `ipc_call` with no buffer for an
+// out-of-line payload, so the reply can't carry one.
+fn handle_ipc_reply(dst_tid: u32, message: &Message) -> KResult<()> {
+    if message.ool_len > 0 {
+        return KResult::NotAcceptable;
+    }
     let task_pool = task::get_task_pool();
     task_pool
         .lookup_task(dst_tid)
-        .and_then(|task| ipc::send(task_pool, task, message, flags))
+        .and_then(|task| ipc::reply(task_pool, task, message))
 }
 
-fn handle_ipc_recv(src_tid: u32, message: &mut Message) -> KResult<()> {
-    if src_tid > config::NUM_TASKS {
+fn handle_ipc_recv(
+    src_tid: u32,
+    message: &mut Message,
+    flags: IpcFlags,
+    timeout: u32,
+) -> KResult<()> {
+    if src_tid >= config::NUM_TASKS {
         return KResult::InvalidArg;
     }
+    check_ool_buffer(message, true)?;
 
     let task_pool = task::get_task_pool();
-    ipc::recv(task_pool, src_tid, message, IpcFlags::block())
+    ipc::recv(task_pool, src_tid, message, flags, timeout)
 }
 
-fn handle_ipc_call(dst_tid: u32, message: &mut Message) -> KResult<()> {
+// The timeout applies to each of the send and the receive phases.
+fn handle_ipc_call(dst_tid: u32, message: &mut Message, timeout: u32) -> KResult<()> {
+    check_ool_buffer(message, false)?;
     let task_pool = task::get_task_pool();
-    task_pool
+    let result = task_pool
         .lookup_task(dst_tid)
-        .and_then(|task| ipc::send(task_pool, task, message, IpcFlags::block()))
-        .and_then(|_| ipc::recv(task_pool, dst_tid, message, IpcFlags::block()))
+        .and_then(|task| ipc::call(task_pool, task, message, timeout));
+    report_ool_capacity(task_pool, message, result)
+}
+
+// As with `DestroyTask`, only the pager of the task or init may abort it.
+fn handle_ipc_abort(tid: u32) -> KResult<()> {
+    let task_pool = task::get_task_pool();
+    let current = task_pool.current().tid();
+    let task = task_pool.lookup_task(tid)?;
+    if task.task_type() == TaskType::Idle
+        || task.tid() == current
+        || (current != task::INIT_TID && current != task.pager())
+    {
+        return KResult::NotPermitted;
+    }
+    ipc::abort(task_pool, task)
 }
 
 // Sends notifications.
@@ -56,7 +148,72 @@ fn handle_notify(dst_tid: u32, notifications: Notifications) -> KResult<()> {
 
 fn handle_create_task(tid: u32, pc: u32, sp: u32) -> KResult<()> {
     let task_pool = task::get_task_pool();
-    return task_pool.create_user_task(tid, pc, sp);
+    task_pool.create_user_task(tid, pc, sp, task_pool.current().tid())
+}
+
+fn handle_create_task_with_stack(tid: u32, pc: u32, stack: &mut [u8]) -> KResult<()> {
+    let task_pool = task::get_task_pool();
+    task_pool.create_user_task_with_stack(tid, pc, stack, task_pool.current().tid())
+}
+
+// Only the creator of the task, i.e. its pager, and init can destroy it.
+fn handle_destroy_task(tid: u32) -> KResult<()> {
+    let task_pool = task::get_task_pool();
+    let current = task_pool.current().tid();
+    task_pool.lookup_task(tid).and_then(|task| {
+        if current != task::INIT_TID && current != task.pager() {
+            return KResult::NotPermitted;
+        }
+        task_pool.destroy_task(task)
+    })
+}
+
+fn handle_exit_task() -> KResult<()> {
+    let task_pool = task::get_task_pool();
+    task_pool.exit_current_task();
+    KResult::Ok(())
+}
+
+fn handle_schedule_task(tid: u32, priority: u32, time_slice: u32) -> KResult<()> {
+    let task_pool = task::get_task_pool();
+    if task_pool.current().tid() != task::INIT_TID {
+        return KResult::NotPermitted;
+    }
+    task_pool
+        .lookup_task(tid)
+        .and_then(|task| task_pool.schedule_task(task, priority, time_slice as i32))
+}
+
+fn handle_irq_acquire(irq: u32) -> KResult<()> {
+    let task_pool = task::get_task_pool();
+    irq::get_irq_table().acquire(task_pool.current(), irq)
+}
+
+fn handle_irq_release(irq: u32) -> KResult<()> {
+    let task_pool = task::get_task_pool();
+    irq::get_irq_table().release(task_pool.current(), irq)
+}
+
+fn handle_irq_ack(irq: u32) -> KResult<()> {
+    let task_pool = task::get_task_pool();
+    irq::get_irq_table().acknowledge(task_pool.current(), irq)
+}
+
+fn handle_task_self() -> KResult<u32> {
+    let task_pool = task::get_task_pool();
+    KResult::Ok(task_pool.current().tid())
+}
+
+fn handle_task_info(tid: u32, info: &mut TaskInfo) -> KResult<()> {
+    let task_pool = task::get_task_pool();
+    task_pool.lookup_task(tid).map(|task| *info = task.info())
+}
+
+fn into_retval(r: KResult<u32>, a1: u32) -> u64 {
+    match r {
+        KResult::Ok(v) => (v as u64) << 32,
+        e => e.err_as_u32() as u64 | ((a1 as u64) << 32),
+    }
 }
 
 #[no_mangle]
@@ -64,40 +221,71 @@ pub extern "C" fn handle_syscall(
     a0: u32,
     a1: u32,
     a2: u32,
-    _a3: u32,
+    a3: u32,
     _a4: u32,
     _a5: u32,
     _syscall_subid: u32,
     syscall_id: u32,
 ) -> u64 {
+    let current = task::get_task_pool().current();
     let r = match syscall_id {
         i if i == Syscall::Nop.as_u32() => KResult::Ok(()),
         i if i == Syscall::SetTimer.as_u32() => handle_set_timer(a0),
-        i if i == Syscall::ConsoleWrite.as_u32() => {
-            handle_console_write(unsafe { slice::from_raw_parts(a0 as *const u8, a1 as usize) })
-        }
-        i if i == Syscall::IpcSend.as_u32() => handle_ipc_send(
-            a0,
-            unsafe { mem::transmute::<u32, &Message>(a1) },
-            IpcFlags::block(),
-        ),
-        i if i == Syscall::IpcRecv.as_u32() => {
-            handle_ipc_recv(a0, unsafe { mem::transmute::<u32, &mut Message>(a1) })
+        i if i == Syscall::TimerSet.as_u32() => handle_timer_set(a0, a1, a2),
+        i if i == Syscall::TimerCancel.as_u32() => handle_timer_cancel(a0),
+        i if i == Syscall::TimerSetUntil.as_u32() => {
+            handle_timer_set_until(a0, ((a2 as u64) << 32) | a1 as u64)
         }
-        i if i == Syscall::IpcCall.as_u32() => {
-            handle_ipc_call(a0, unsafe { mem::transmute::<u32, &mut Message>(a1) })
-        }
-        i if i == Syscall::IpcSendNoblock.as_u32() => handle_ipc_send(
-            a0,
-            unsafe { mem::transmute::<u32, &mut Message>(a1) },
-            IpcFlags::noblock(),
-        ),
+        i if i == Syscall::GetTime.as_u32() => UserPtr::<u64>::new(a0)
+            .as_mut(current)
+            .and_then(handle_get_time),
+        i if i == Syscall::ConsoleWrite.as_u32() => UserSlice::new(a0, a1)
+            .as_slice(current)
+            .and_then(handle_console_write),
+        i if i == Syscall::IpcSend.as_u32() => UserPtr::<Message>::new(a1)
+            .as_mut(current)
+            .and_then(|message| handle_ipc_send(a0, message, IpcFlags::block(), 0)),
+        i if i == Syscall::IpcRecv.as_u32() => UserPtr::<Message>::new(a1)
+            .as_mut(current)
+            .and_then(|message| handle_ipc_recv(a0, message, IpcFlags::block(), 0)),
+        i if i == Syscall::IpcRecvNoblock.as_u32() => UserPtr::<Message>::new(a1)
+            .as_mut(current)
+            .and_then(|message| handle_ipc_recv(a0, message, IpcFlags::noblock(), 0)),
+        i if i == Syscall::IpcRecvTimeout.as_u32() => UserPtr::<Message>::new(a1)
+            .as_mut(current)
+            .and_then(|message| handle_ipc_recv(a0, message, IpcFlags::block(), a2)),
+        i if i == Syscall::IpcCall.as_u32() => UserPtr::<Message>::new(a1)
+            .as_mut(current)
+            .and_then(|message| handle_ipc_call(a0, message, 0)),
+        i if i == Syscall::IpcSendNoblock.as_u32() => UserPtr::<Message>::new(a1)
+            .as_mut(current)
+            .and_then(|message| handle_ipc_send(a0, message, IpcFlags::noblock(), 0)),
+        i if i == Syscall::IpcSendTimeout.as_u32() => UserPtr::<Message>::new(a1)
+            .as_mut(current)
+            .and_then(|message| handle_ipc_send(a0, message, IpcFlags::block(), a2)),
+        i if i == Syscall::IpcCallTimeout.as_u32() => UserPtr::<Message>::new(a1)
+            .as_mut(current)
+            .and_then(|message| handle_ipc_call(a0, message, a2)),
+        i if i == Syscall::IpcReply.as_u32() => UserPtr::<Message>::new(a1)
+            .as_ref(current)
+            .and_then(|message| handle_ipc_reply(a0, message)),
+        i if i == Syscall::IpcAbort.as_u32() => handle_ipc_abort(a0),
         i if i == Syscall::Notify.as_u32() => handle_notify(a0, Notifications::from_u32(a1)),
         i if i == Syscall::CreateTask.as_u32() => handle_create_task(a0, a1, a2),
+        i if i == Syscall::CreateTaskWithStack.as_u32() => UserSlice::new(a2, a3)
+            .as_mut_slice(current)
+            .and_then(|stack| handle_create_task_with_stack(a0, a1, stack)),
+        i if i == Syscall::DestroyTask.as_u32() => handle_destroy_task(a0),
+        i if i == Syscall::ExitTask.as_u32() => handle_exit_task(),
+        i if i == Syscall::IrqAquire.as_u32() => handle_irq_acquire(a0),
+        i if i == Syscall::IrqRelease.as_u32() => handle_irq_release(a0),
+        i if i == Syscall::IrqAck.as_u32() => handle_irq_ack(a0),
+        i if i == Syscall::TaskSelf.as_u32() => return into_retval(handle_task_self(), a1),
+        i if i == Syscall::ScheduleTask.as_u32() => handle_schedule_task(a0, a1, a2),
+        i if i == Syscall::TaskInfo.as_u32() => UserPtr::<TaskInfo>::new(a1)
+            .as_mut(current)
+            .and_then(|info| handle_task_info(a0, info)),
         _ => KResult::InvalidArg,
     };
-    match r {
-        KResult::Ok(()) => (a1 as u64) << 32,
-        e => e.err_as_u32() as u64 | ((a1 as u64) << 32),
-    }
+    into_retval(r.map(|_| a1), a1)
 }
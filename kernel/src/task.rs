@@ -2,18 +2,24 @@ use crate::arch::task::ArchTask;
 pub use crate::arch::task::Task;
 use crate::config;
 use crate::ipc;
+use crate::irq;
+use crate::kpanic;
+use crate::timer::{self, Timer, TimerKind};
 use core::cell::Cell;
-use core::mem;
-use klib::ipc::{Message, MessageType, Notifications};
+use core::{mem, slice};
+use klib::ipc::{Message, MessageType, NotificationPayload, Notifications};
 use klib::list::{self, RemovableLinkedStackOps};
 use klib::result::KResult;
+pub use klib::task::{TaskInfo, TaskState, TaskType, NOT_SENDING, TASK_PRIORITY_MAX};
 use klib::zeroed_array;
 
-const TASK_PRIORITY_MAX: u32 = 8;
 const TASK_TIME_SLICE: i32 = 10; // should meet timer intr cycle
 pub const KERNEL_TID: u32 = 0;
 pub const INIT_TID: u32 = 1;
 
+// Fills the registered user stacks to measure their peak usage.
+const USER_STACK_FILL: u8 = 0xa5;
+
 pub type TaskRef = &'static Task;
 type TaskList = [Task; config::NUM_TASKS as usize];
 type RunQueue = list::ListLink<'static, Task>;
@@ -95,12 +101,46 @@ impl TaskPool {
         KResult::Ok(())
     }
 
-    pub fn create_user_task(&self, tid: u32, pc: u32, sp: u32) -> KResult<()> {
-        if tid > config::NUM_TASKS {
+    // `pager` receives exception messages of the task. `KERNEL_TID` means none.
+    pub fn create_user_task(&self, tid: u32, pc: u32, sp: u32, pager: u32) -> KResult<()> {
+        if tid >= config::NUM_TASKS {
+            return KResult::InvalidArg;
+        }
+        Self::initiate_task(tid, self.tasks.task(tid), pc, sp).map(|_| {
+            let task = self.tasks.task(tid);
+            task.noarch().pager.set(pager);
+            self.resume_task(task)
+        })
+    }
+
+    // Creates a user task running on `stack`. The stack is filled with a
+    // pattern to measure its peak usage, and its lowest
+    // `USER_STACK_GUARD_SIZE` bytes are made inaccessible to the task where
+    // the arch supports it.
+    pub fn create_user_task_with_stack(
+        &self,
+        tid: u32,
+        pc: u32,
+        stack: &mut [u8],
+        pager: u32,
+    ) -> KResult<()> {
+        let bottom = stack.as_ptr() as u32;
+        let size = stack.len() as u32;
+        if tid >= config::NUM_TASKS
+            || bottom % 16 != 0
+            || size % 16 != 0
+            || size <= config::USER_STACK_GUARD_SIZE
+        {
             return KResult::InvalidArg;
         }
-        Self::initiate_task(tid, self.tasks.task(tid), pc, sp)
-            .map(|_| self.resume_task(self.tasks.task(tid)))
+        Self::initiate_task(tid, self.tasks.task(tid), pc, bottom + size).map(|_| {
+            let task = self.tasks.task(tid);
+            stack.fill(USER_STACK_FILL);
+            task.noarch().stack_bottom.set(bottom);
+            task.noarch().stack_size.set(size);
+            task.noarch().pager.set(pager);
+            self.resume_task(task)
+        })
     }
 
     pub fn create_idle_task(&self) -> KResult<()> {
@@ -120,14 +160,149 @@ impl TaskPool {
         list.push_back(task);
     }
 
-    // Suspends a task. Don't forget to update `task->src` as well!
+    // The current task is runnable but is not linked into any runqueue while running.
+    fn in_runqueue(&self, task: TaskRef) -> bool {
+        task.state() == TaskState::Runnable && task.tid() != self.current().tid()
+    }
+
+    fn dequeue_task(&self, task: TaskRef) {
+        let mut list = self.list_for_runqueue(task.priority());
+        list.remove(task);
+    }
+
+    pub fn has_higher_priority_task(&self, task: TaskRef) -> bool {
+        (0..task.priority()).any(|priority| !self.list_for_runqueue(priority).empty())
+    }
+
+    pub fn schedule_task(&self, task: TaskRef, priority: u32, time_slice: i32) -> KResult<()> {
+        if priority >= TASK_PRIORITY_MAX || time_slice < 0 {
+            return KResult::InvalidArg;
+        }
+        if task.task_type() == TaskType::Idle {
+            return KResult::NotPermitted;
+        }
+        task.noarch().base_priority.set(priority);
+        task.noarch().time_slice.set(if time_slice == 0 {
+            TASK_TIME_SLICE
+        } else {
+            time_slice
+        });
+        self.update_priority(task);
+        KResult::Ok(())
+    }
+
+    fn set_priority(&self, task: TaskRef, priority: u32) {
+        let queued = self.in_runqueue(task);
+        if queued {
+            self.dequeue_task(task);
+        }
+        task.noarch().priority.set(priority);
+        if queued {
+            self.enqueue_task(task);
+        }
+    }
+
+    // Returns the task blocking `task` in IPC which inherits its priority: the
+    // receiver in whose sender queue it is, or the server it waits for the
+    // reply from in `ipc_call`. A task waiting in a plain receive lends its
+    // priority to nobody.
+    fn waited_task(&self, task: TaskRef) -> Option<TaskRef> {
+        if task.state() != TaskState::Blocked {
+            return None;
+        }
+        let tid = if task.dst_tid() != NOT_SENDING {
+            task.dst_tid()
+        } else if task.in_call() {
+            task.src_tid()
+        } else {
+            return None;
+        };
+        if tid == 0 || tid >= config::NUM_TASKS {
+            // Waiting for any task, or for none.
+            return None;
+        }
+        let waited = self.tasks.task(tid);
+        (waited.state() != TaskState::Unused).then_some(waited)
+    }
+
+    // Priority inheritance: a task runs at the highest priority of its own and
+    // the tasks waiting for it, and so do the tasks it waits for in turn. The
+    // chain is bounded in case tasks wait for each other in a cycle.
+    fn inherit_priority(&self, task: TaskRef, priority: u32) {
+        let mut next = Some(task);
+        for _ in 0..config::NUM_TASKS {
+            match next {
+                Some(task) if priority < task.priority() => {
+                    self.set_priority(task, priority);
+                    next = self.waited_task(task);
+                }
+                _ => return,
+            }
+        }
+    }
+
+    // Called when `waiter` no longer waits for `task`.
+    fn disinherit_priority(&self, task: TaskRef, waiter: TaskRef) {
+        if task.priority() < task.base_priority() && waiter.priority() <= task.priority() {
+            self.update_priority(task);
+        }
+    }
+
+    // Recomputes the effective priority of `task` from the tasks waiting for
+    // it: queued in its senders or waiting for its reply in `ipc_call`.
+    fn update_priority(&self, task: TaskRef) {
+        let mut next = Some(task);
+        for _ in 0..config::NUM_TASKS {
+            let Some(task) = next else {
+                return;
+            };
+            let senders = self.list_for_senders(task);
+            let callers = self.active_tasks().filter(|waiter| {
+                waiter.state() == TaskState::Blocked
+                    && waiter.in_call()
+                    && waiter.src_tid() == task.tid()
+            });
+            let priority = senders
+                .iter()
+                .chain(callers)
+                .map(|waiter| waiter.priority())
+                .fold(task.base_priority(), u32::min);
+            if priority == task.priority() {
+                return;
+            }
+            self.set_priority(task, priority);
+            next = self.waited_task(task);
+        }
+    }
+
+    // Suspends a task. Update `task->src` and `task->dst` beforehand: the task
+    // it waits for inherits its priority.
     pub fn block_task(&self, task: TaskRef) {
         task.noarch().state.set(TaskState::Blocked);
+        if let Some(waited) = self.waited_task(task) {
+            self.inherit_priority(waited, task.priority());
+        }
     }
 
     pub fn resume_task(&self, task: TaskRef) {
+        let waited = self.waited_task(task);
+        // The task no longer waits for the IPC timeout.
+        timer::get_timer_queue().remove(&task.noarch().ipc_timer);
         task.noarch().state.set(TaskState::Runnable);
         self.enqueue_task(task);
+        if let Some(waited) = waited {
+            self.disinherit_priority(waited, task);
+        }
+        self.update_tick();
+    }
+
+    // The periodic tick is needed only for preempting the running task. While
+    // no other task is runnable, we only need the timer interrupt for the
+    // earliest deadline.
+    fn update_tick(&self) {
+        let tickless =
+            (0..TASK_PRIORITY_MAX).all(|priority| self.list_for_runqueue(priority).empty());
+        timer::get_timer_queue().set_tickless(tickless);
     }
 
     fn scheduler(&self, current: TaskRef) -> TaskRef {
@@ -145,12 +320,13 @@ impl TaskPool {
     }
 
     pub fn task_switch(&self) {
-        // stack_check();
+        stack_check(self.current());
 
         let prev: TaskRef = self.current();
         let next: TaskRef = self.scheduler(prev);
 
-        next.noarch().quantum.set(TASK_TIME_SLICE);
+        next.noarch().quantum.set(next.time_slice());
+        self.update_tick();
         if prev.tid() == next.tid() {
             // No runnable threads other than the current one. Continue executing
             // the current thread.
@@ -159,14 +335,105 @@ impl TaskPool {
 
         Task::arch_task_switch(prev, next);
 
-        // stack_check();
+        stack_check(self.current());
+    }
+
+    // Switches to `next` woken up from IPC without going through the runqueues,
+    // donating the remaining quantum of the current task. Callers check that no
+    // task has a higher priority than `next`.
+    pub fn switch_to(&self, next: TaskRef) {
+        stack_check(self.current());
+
+        let prev: TaskRef = self.current();
+        let waited = self.waited_task(next);
+        timer::get_timer_queue().remove(&next.noarch().ipc_timer);
+        next.noarch().state.set(TaskState::Runnable);
+        if let Some(waited) = waited {
+            // Typically `prev` replying to `next`.
+            self.disinherit_priority(waited, next);
+        }
+        if prev.task_type() != TaskType::Idle && prev.state() == TaskState::Runnable {
+            self.enqueue_task(prev);
+        }
+        next.noarch().quantum.set(prev.quantum());
+        self.update_tick();
+
+        Task::arch_task_switch(prev, next);
+
+        stack_check(self.current());
     }
 
-    pub fn set_current_timeout(&self, timeout: u32) -> KResult<()> {
-        self.current().noarch().timeout.set(timeout);
+    // Arms the timer `id` of `task`. See `TimerQueue::add`.
+    pub fn set_timer(&self, task: TaskRef, id: u32, ms: u32, period: u32) -> KResult<()> {
+        if id >= config::NUM_TIMERS_PER_TASK {
+            return KResult::InvalidArg;
+        }
+        self.cancel_timer(task, id)?;
+        timer::get_timer_queue().add(task_timer(task, id), ms, period);
         KResult::Ok(())
     }
 
+    // Arms the timer `id` of `task` to expire at `deadline` (in machine timer
+    // counts). A deadline in the past fires immediately.
+    pub fn set_timer_until(&self, task: TaskRef, id: u32, deadline: u64) -> KResult<()> {
+        if id >= config::NUM_TIMERS_PER_TASK {
+            return KResult::InvalidArg;
+        }
+        self.cancel_timer(task, id)?;
+        timer::get_timer_queue().add_at(task_timer(task, id), deadline);
+        KResult::Ok(())
+    }
+
+    pub fn cancel_timer(&self, task: TaskRef, id: u32) -> KResult<()> {
+        if id >= config::NUM_TIMERS_PER_TASK {
+            return KResult::InvalidArg;
+        }
+        timer::get_timer_queue().remove(task_timer(task, id));
+        // Forget the expiration not yet delivered.
+        task.noarch()
+            .fired_timers
+            .update(|fired| fired & !(1 << id));
+        KResult::Ok(())
+    }
+
+    // Returns the bitmap of fired timers and clears it.
+    pub fn take_fired_timers(&self, task: TaskRef) -> u32 {
+        task.noarch().fired_timers.replace(0)
+    }
+
+    pub fn set_ipc_timeout(&self, task: TaskRef, timeout: u32) {
+        timer::get_timer_queue().add(&task.noarch().ipc_timer, timeout, 0);
+    }
+
+    // Rearms the IPC timeout of `task` to expire at `deadline` returned by
+    // `TaskOps::ipc_deadline`. Zero means no timeout.
+    pub fn set_ipc_deadline(&self, task: TaskRef, deadline: u64) {
+        if deadline != 0 {
+            timer::get_timer_queue().add_at(&task.noarch().ipc_timer, deadline);
+        }
+    }
+
+    pub fn set_ipc_timed_out(&self, task: TaskRef, timed_out: bool) {
+        task.noarch().ipc_timed_out.set(timed_out);
+    }
+
+    pub fn set_in_call(&self, task: TaskRef, in_call: bool) {
+        task.noarch().in_call.set(in_call);
+    }
+
+    pub fn set_message_taken(&self, task: TaskRef, taken: bool) {
+        task.noarch().message_taken.set(taken);
+    }
+
+    pub fn set_ool_buffer(&self, task: TaskRef, ptr: usize, len: usize) {
+        task.noarch().ool_ptr.set(ptr);
+        task.noarch().ool_len.set(len);
+    }
+
+    pub fn set_ool_too_large(&self, task: TaskRef, too_large: bool) {
+        task.noarch().ool_too_large.set(too_large);
+    }
+
     pub fn set_src_tid(&self, task: TaskRef, src_tid: u32) {
         task.noarch().src_tid.set(src_tid);
     }
@@ -179,10 +446,82 @@ impl TaskPool {
     }
 
     pub fn append_sender(&self, task: TaskRef, appended: TaskRef) {
+        appended.noarch().dst_tid.set(task.tid());
         let mut list = self.list_for_senders(task);
         list.push_back(appended);
     }
 
+    pub fn remove_sender(&self, task: TaskRef, removed: TaskRef) {
+        let mut list = self.list_for_senders(task);
+        list.remove(removed);
+        removed.noarch().dst_tid.set(NOT_SENDING);
+        self.disinherit_priority(task, removed);
+    }
+
+    // Wakes up a task blocked in IPC and makes the operation return `KResult::Aborted`.
+    pub fn abort_ipc(&self, task: TaskRef) {
+        self.update_notifications(task, |n| n | Notifications::aborted());
+        self.resume_task(task);
+    }
+
+    pub fn destroy_task(&self, task: TaskRef) -> KResult<()> {
+        if task.task_type() == TaskType::Idle || task.tid() == INIT_TID {
+            return KResult::NotPermitted;
+        }
+        if task.tid() == self.current().tid() {
+            // Use `exit_current_task` instead.
+            return KResult::InvalidArg;
+        }
+        self.release_task(task);
+        KResult::Ok(())
+    }
+
+    pub fn exit_current_task(&self) {
+        self.release_task(self.current());
+        // Never returns: the task is no longer runnable.
+        self.task_switch();
+    }
+
+    fn release_task(&self, task: TaskRef) {
+        let waited = self.waited_task(task);
+        match task.state() {
+            TaskState::Runnable if self.in_runqueue(task) => self.dequeue_task(task),
+            TaskState::Blocked if task.dst_tid() != NOT_SENDING => {
+                self.remove_sender(self.tasks.task(task.dst_tid()), task);
+            }
+            _ => (),
+        }
+        // The task is in no runqueue anymore: aborting its waiters below must
+        // not requeue it.
+        task.noarch().priority.set(task.base_priority());
+
+        // Abort tasks blocked on sending to the task.
+        let mut senders = self.list_for_senders(task);
+        while let Some(sender) = senders.pop_front() {
+            sender.noarch().dst_tid.set(NOT_SENDING);
+            self.abort_ipc(sender);
+        }
+
+        // Abort tasks waiting for a message from the task (e.g. a reply of ipc_call).
+        for waiter in self
+            .active_tasks()
+            .filter(|waiter| waiter.state() == TaskState::Blocked && waiter.src_tid() == task.tid())
+        {
+            self.abort_ipc(waiter);
+        }
+
+        irq::get_irq_table().release_all(task);
+        for timer in timer::task_timers(task.tid()) {
+            timer::get_timer_queue().remove(timer);
+        }
+        timer::get_timer_queue().remove(&task.noarch().ipc_timer);
+        task.noarch().notifications.set(Notifications::none());
+        task.noarch().state.set(TaskState::Unused);
+        if let Some(waited) = waited {
+            self.disinherit_priority(waited, task);
+        }
+    }
+
     pub fn update_notifications<F: FnOnce(Notifications) -> Notifications>(
         &self,
         task: TaskRef,
@@ -192,7 +531,7 @@ impl TaskPool {
     }
 
     pub fn lookup_task(&self, tid: u32) -> KResult<TaskRef> {
-        if tid > config::NUM_TASKS {
+        if tid >= config::NUM_TASKS {
             KResult::InvalidArg
         } else {
             let task = self.tasks.task(tid);
@@ -215,7 +554,7 @@ struct ActiveTasks<'a> {
 }
 
 impl<'a> Iterator for ActiveTasks<'a> {
-    type Item = &'a Task;
+    type Item = TaskRef;
 
     fn next(&mut self) -> Option<Self::Item> {
         while self.tid < config::NUM_TASKS
@@ -238,39 +577,54 @@ pub struct NoarchTask {
     task_type: Cell<TaskType>,
     state: Cell<TaskState>,
     notifications: Cell<Notifications>,
+    // The priority set by `schedule_task`. `priority` is the effective one,
+    // inherited from the tasks waiting for this task in IPC.
+    base_priority: Cell<u32>,
     priority: Cell<u32>,
+    time_slice: Cell<i32>,
     quantum: Cell<i32>,
     message: Cell<Message>,
     src_tid: Cell<u32>,
-    timeout: Cell<u32>,
+    dst_tid: Cell<u32>,
+    pager: Cell<u32>,
+    stack_bottom: Cell<u32>,
+    stack_size: Cell<u32>,
+    ipc_timer: Timer,
+    fired_timers: Cell<u32>,
+    ipc_timed_out: Cell<bool>,
+    // Set while the task is in `ipc::call`: only such a task accepts
+    // `ipc::reply`.
+    in_call: Cell<bool>,
+    // Set when a receiver has taken the message of the task queued in
+    // sending, which is kept in `message` meanwhile.
+    message_taken: Cell<bool>,
+    // The out-of-line buffer of the pending IPC: the address and the capacity
+    // to receive into when receiving, or the payload to send when queued in
+    // sending.
+    ool_ptr: Cell<usize>,
+    ool_len: Cell<usize>,
+    // Set when a receiver rejects the out-of-line payload of the task queued
+    // in sending.
+    ool_too_large: Cell<bool>,
     senders: list::ListLink<'static, Task>,
     runqueue_link: list::ListLink<'static, Task>,
     sender_link: list::ListLink<'static, Task>,
 }
 
-#[derive(PartialEq, Clone, Copy)]
-pub enum TaskState {
-    Unused = 0,
-    Runnable,
-    Blocked,
-}
-
-#[derive(PartialEq, Clone, Copy)]
-pub enum TaskType {
-    Idle = 0,
-    User,
-}
-
 pub trait NotificationMessage {
-    fn set_notification(&mut self, notifications: Notifications);
+    fn set_notification(&mut self, notifications: Notifications, fired_timers: u32);
 }
 
 impl NotificationMessage for Message {
-    fn set_notification(&mut self, notifications: Notifications) {
+    fn set_notification(&mut self, notifications: Notifications, fired_timers: u32) {
         self.message_type = MessageType::NOTIFICATIONS;
         self.src_tid = KERNEL_TID;
+        self.ool_len = 0;
         self.raw.fill(0);
-        self.set_payload(&notifications);
+        self.set_payload(&NotificationPayload {
+            notifications,
+            fired_timers,
+        });
     }
 }
 
@@ -281,47 +635,125 @@ pub trait GetNoarchTask {
 pub trait TaskOps {
     fn init(tid: u32, task: TaskRef, pc: u32, sp: u32) -> KResult<()>;
     fn tid(&self) -> u32;
+    fn base_priority(&self) -> u32;
     fn priority(&self) -> u32;
+    fn time_slice(&self) -> i32;
     fn quantum(&self) -> i32;
     fn timeout(&self) -> u32;
+    fn ipc_deadline(&self) -> u64;
+    fn ipc_timed_out(&self) -> bool;
+    fn in_call(&self) -> bool;
+    fn message_taken(&self) -> bool;
+    fn ool_buffer(&self) -> (usize, usize);
+    fn ool_too_large(&self) -> bool;
     fn src_tid(&self) -> u32;
+    fn dst_tid(&self) -> u32;
+    fn pager(&self) -> u32;
+    fn user_stack(&self) -> (u32, u32);
+    fn user_stack_peak(&self) -> u32;
     fn task_type(&self) -> TaskType;
     fn state(&self) -> TaskState;
     fn notifications(&self) -> Notifications;
+    fn info(&self) -> TaskInfo;
 }
 
 impl TaskOps for Task {
     fn init(tid: u32, task: TaskRef, pc: u32, sp: u32) -> KResult<()> {
+        // Fails before touching the task, which stays unused.
+        Task::arch_task_init(tid, task, pc, sp)?;
         task.noarch().tid.set(tid);
         task.noarch().task_type.set(TaskType::User);
         task.noarch().state.set(TaskState::Blocked);
+        task.noarch().base_priority.set(TASK_PRIORITY_MAX - 1);
         task.noarch().priority.set(TASK_PRIORITY_MAX - 1);
+        task.noarch().time_slice.set(TASK_TIME_SLICE);
         task.noarch().quantum.set(0);
-        task.noarch().message.set(unsafe { mem::zeroed() });
+        task.noarch().message.set(Message::zeroed());
         task.noarch().notifications.set(Notifications::none());
         task.noarch().src_tid.set(0);
-        task.noarch().timeout.set(0);
+        task.noarch().dst_tid.set(NOT_SENDING);
+        task.noarch().pager.set(KERNEL_TID);
+        task.noarch().stack_bottom.set(0);
+        task.noarch().stack_size.set(0);
+        for (id, timer) in timer::task_timers(tid).iter().enumerate() {
+            timer.init(tid, id as u32, TimerKind::Notify);
+        }
+        task.noarch().ipc_timer.init(tid, 0, TimerKind::Ipc);
+        task.noarch().fired_timers.set(0);
+        task.noarch().ipc_timed_out.set(false);
+        task.noarch().in_call.set(false);
+        task.noarch().message_taken.set(false);
+        task.noarch().ool_ptr.set(0);
+        task.noarch().ool_len.set(0);
+        task.noarch().ool_too_large.set(false);
         task.noarch().senders.reset();
         task.noarch().runqueue_link.reset();
         task.noarch().sender_link.reset();
-        Task::arch_task_init(tid, task, pc, sp)
+        KResult::Ok(())
     }
 
     fn tid(&self) -> u32 {
         self.noarch().tid.get()
     }
+    fn base_priority(&self) -> u32 {
+        self.noarch().base_priority.get()
+    }
     fn priority(&self) -> u32 {
         self.noarch().priority.get()
     }
+    fn time_slice(&self) -> i32 {
+        self.noarch().time_slice.get()
+    }
     fn quantum(&self) -> i32 {
         self.noarch().quantum.get()
     }
     fn timeout(&self) -> u32 {
-        self.noarch().timeout.get()
+        // The timer used by the `SetTimer` syscall.
+        timer::task_timers(self.tid())[0].remaining_ms()
+    }
+    fn ipc_deadline(&self) -> u64 {
+        self.noarch().ipc_timer.deadline()
+    }
+    fn ipc_timed_out(&self) -> bool {
+        self.noarch().ipc_timed_out.get()
+    }
+    fn in_call(&self) -> bool {
+        self.noarch().in_call.get()
+    }
+    fn message_taken(&self) -> bool {
+        self.noarch().message_taken.get()
+    }
+    fn ool_buffer(&self) -> (usize, usize) {
+        (self.noarch().ool_ptr.get(), self.noarch().ool_len.get())
+    }
+    fn ool_too_large(&self) -> bool {
+        self.noarch().ool_too_large.get()
     }
     fn src_tid(&self) -> u32 {
         self.noarch().src_tid.get()
     }
+    fn dst_tid(&self) -> u32 {
+        self.noarch().dst_tid.get()
+    }
+    fn pager(&self) -> u32 {
+        self.noarch().pager.get()
+    }
+    // Returns the bottom and the size of the registered stack.
+    fn user_stack(&self) -> (u32, u32) {
+        (
+            self.noarch().stack_bottom.get(),
+            self.noarch().stack_size.get(),
+        )
+    }
+    fn user_stack_peak(&self) -> u32 {
+        let (bottom, size) = self.user_stack();
+        if size == 0 {
+            return 0;
+        }
+        let stack = unsafe { slice::from_raw_parts(bottom as *const u8, size as usize) };
+        let unused = stack.iter().take_while(|b| **b == USER_STACK_FILL).count();
+        size - unused as u32
+    }
     fn task_type(&self) -> TaskType {
         self.noarch().task_type.get()
     }
@@ -331,30 +763,71 @@ impl TaskOps for Task {
     fn notifications(&self) -> Notifications {
         self.noarch().notifications.get()
     }
+    fn info(&self) -> TaskInfo {
+        TaskInfo {
+            tid: self.tid(),
+            state: self.state(),
+            task_type: self.task_type(),
+            priority: self.priority(),
+            time_slice: self.time_slice(),
+            quantum: self.quantum(),
+            notifications: self.notifications(),
+            src_tid: self.src_tid(),
+            dst_tid: self.dst_tid(),
+            timeout: self.timeout(),
+            pager: self.pager(),
+            kernel_stack_peak: Task::arch_kernel_stack_peak(self),
+            stack_size: self.user_stack().1,
+            stack_peak: self.user_stack_peak(),
+            base_priority: self.base_priority(),
+        }
+    }
+}
+
+// `id` must be less than `NUM_TIMERS_PER_TASK`: checked by the callers so that
+// there's no panic path.
+fn task_timer(task: TaskRef, id: u32) -> &'static Timer {
+    unsafe { timer::task_timers(task.tid()).get_unchecked(id as usize) }
 }
 
 pub fn get_task_pool() -> &'static TaskPool {
     unsafe { &TASK_POOL }
 }
 
+// Panics if the kernel stack of `task` has overflowed into the one below it.
+pub fn stack_check(task: TaskRef) {
+    if !Task::arch_stack_canary_ok(task) {
+        kpanic!(b"kernel stack overflow in task {}\n", task.tid());
+    }
+}
+
 pub fn handle_timer_irq() {
     let task_pool = get_task_pool();
-
-    let resumed_by_timeout = task_pool
-        .active_tasks()
-        .filter(|task: &&Task| task.timeout() > 0)
-        .filter(|task| {
-            let next_timeout = task.timeout() - 1;
-            task.noarch().timeout.set(next_timeout);
-            next_timeout == 0
+    let timer_queue = timer::get_timer_queue();
+    let now = timer::now();
+
+    let resumed_by_timeout = core::iter::from_fn(|| timer_queue.pop_expired(now))
+        .map(|timer| {
+            let task = task_pool.tasks.task(timer.tid());
+            match timer.kind() {
+                TimerKind::Notify => {
+                    task.noarch()
+                        .fired_timers
+                        .update(|fired| fired | (1 << timer.id()));
+                    ipc::notify(task_pool, task, Notifications::timer());
+                }
+                TimerKind::Ipc => ipc::time_out(task_pool, task),
+            }
         })
-        .map(|task| ipc::notify(task_pool, task, Notifications::timer()))
         .count()
         > 0;
 
     let current = task_pool.current();
     current.noarch().quantum.update(|quantum| quantum - 1);
-    if current.quantum() < 0 || resumed_by_timeout {
+    if current.quantum() < 0 || resumed_by_timeout || task_pool.has_higher_priority_task(current) {
         task_pool.task_switch();
+    } else {
+        // Program the next one-shot deadline if the tick is stopped.
+        task_pool.update_tick();
     }
 }
@@ -0,0 +1,237 @@
+use crate::arch::mock::task::{add_user_region, run_kernel, spawn, switches};
+use crate::arch::task::{ArchTask, Task};
+use crate::config;
+use crate::ipc;
+use crate::syscall;
+use crate::task::{self, TaskOps, TaskRef, TaskState};
+use klib::ipc::{IpcFlags, Message, MessageType};
+use klib::result::KResult;
+use klib::syscall::Syscall;
+use std::sync::mpsc;
+
+fn lookup(tid: u32) -> TaskRef {
+    task::get_task_pool().lookup_task(tid).ok().unwrap()
+}
+
+fn empty_message() -> Message {
+    Message {
+        message_type: MessageType(100),
+        ..Message::zeroed()
+    }
+}
+
+fn send(dst_tid: u32) -> KResult<()> {
+    let task_pool = task::get_task_pool();
+    ipc::send(
+        task_pool,
+        lookup(dst_tid),
+        &empty_message(),
+        IpcFlags::block(),
+        0,
+    )
+}
+
+fn recv(src_tid: u32) -> KResult<Message> {
+    let mut message = empty_message();
+    ipc::recv(
+        task::get_task_pool(),
+        src_tid,
+        &mut message,
+        IpcFlags::block(),
+        0,
+    )
+    .map(|_| message)
+}
+
+#[test]
+fn task_runs_in_creation_order() {
+    run_kernel(|| {
+        spawn(3, || {});
+        spawn(1, || {});
+        spawn(2, || {});
+        task::get_task_pool().task_switch();
+        assert_eq!(switches(), [(0, 3), (3, 1), (1, 2), (2, 0)]);
+        assert!(matches!(
+            task::get_task_pool().lookup_task(1),
+            KResult::InvalidTask
+        ));
+    });
+}
+
+#[test]
+fn task_higher_priority_runs_first() {
+    run_kernel(|| {
+        let task_pool = task::get_task_pool();
+        spawn(1, || {});
+        spawn(2, || {});
+        assert!(task_pool.schedule_task(lookup(2), 0, 0).is_ok());
+        task_pool.task_switch();
+        assert_eq!(switches(), [(0, 2), (2, 1), (1, 0)]);
+    });
+}
+
+#[test]
+fn task_preempted_when_quantum_runs_out() {
+    run_kernel(|| {
+        spawn(1, || {
+            let time_slice = lookup(1).time_slice();
+            for _ in 0..time_slice {
+                task::handle_timer_irq();
+            }
+            assert_eq!(switches().len(), 1);
+            task::handle_timer_irq();
+            assert_eq!(switches().len(), 3);
+        });
+        spawn(2, || {});
+        task::get_task_pool().task_switch();
+        assert_eq!(switches(), [(0, 1), (1, 2), (2, 1), (1, 0)]);
+    });
+}
+
+#[test]
+fn task_destroy_aborts_ipc() {
+    let (tx, rx) = mpsc::channel();
+    run_kernel(|| {
+        let task_pool = task::get_task_pool();
+        let sender_tx = tx.clone();
+        // Blocked on sending to the task 4.
+        spawn(2, move || {
+            sender_tx.send(matches!(send(4), KResult::Aborted)).unwrap()
+        });
+        // Blocked on receiving from the task 4.
+        spawn(3, move || {
+            tx.send(matches!(recv(4), KResult::Aborted)).unwrap()
+        });
+        spawn(4, || {
+            let _ = recv(5);
+        });
+        task_pool.task_switch();
+        assert!(lookup(2).state() == TaskState::Blocked);
+        assert!(lookup(3).state() == TaskState::Blocked);
+
+        assert!(task_pool.destroy_task(lookup(4)).is_ok());
+        task_pool.task_switch();
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), [true, true]);
+    });
+}
+
+#[test]
+fn task_destroy_removes_queued_sender() {
+    let (tx, rx) = mpsc::channel();
+    run_kernel(|| {
+        let task_pool = task::get_task_pool();
+        spawn(2, || {
+            let _ = send(1);
+        });
+        spawn(3, || assert!(send(1).is_ok()));
+        spawn(1, move || {
+            assert!(matches!(recv(4), KResult::Aborted));
+            tx.send(recv(0).ok().unwrap().src_tid).unwrap();
+        });
+        task_pool.task_switch();
+
+        // Destroy the first sender in the queue. The receiver gets the
+        // message from the next one.
+        assert!(task_pool.destroy_task(lookup(2)).is_ok());
+        assert!(ipc::abort(task_pool, lookup(1)).is_ok());
+        task_pool.task_switch();
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), [3]);
+    });
+}
+
+#[test]
+fn task_create_rejects_out_of_range_tid() {
+    run_kernel(|| {
+        let task_pool = task::get_task_pool();
+        let mut stack = [0u128; 4];
+        let stack = unsafe { std::slice::from_raw_parts_mut(stack.as_mut_ptr() as *mut u8, 64) };
+        assert!(matches!(
+            task_pool.create_user_task(config::NUM_TASKS, 0, 0, task::KERNEL_TID),
+            KResult::InvalidArg
+        ));
+        assert!(matches!(
+            task_pool.create_user_task_with_stack(config::NUM_TASKS, 0, stack, task::KERNEL_TID),
+            KResult::InvalidArg
+        ));
+    });
+}
+
+// Issues a syscall from the current task and returns the error code.
+fn syscall(id: Syscall, a0: u32, a1: u32) -> u32 {
+    syscall::handle_syscall(a0, a1, 0, 0, 0, 0, 0, id.as_u32()) as u32
+}
+
+fn invalid_arg() -> u32 {
+    KResult::<()>::InvalidArg.err_as_u32()
+}
+
+// The regions are not mapped on the host: the test crashes if the kernel
+// touches the memory after all.
+const DATA: u32 = 0x1000_0000;
+const RODATA: u32 = 0x2000_0000;
+const REGION_SIZE: u32 = 0x100;
+
+fn add_regions(tid: u32) {
+    add_user_region(tid, DATA, REGION_SIZE, true);
+    add_user_region(tid, RODATA, REGION_SIZE, false);
+}
+
+#[test]
+fn task_rejects_message_out_of_range() {
+    run_kernel(|| {
+        spawn(2, || {});
+        spawn(1, || {
+            add_regions(1);
+            let size = std::mem::size_of::<Message>() as u32;
+            assert!(Task::arch_user_access_ok(lookup(1), DATA, size, true));
+            // Outside of the regions, and running off the end of one.
+            assert_eq!(syscall(Syscall::IpcSend, 2, 0x3000_0000), invalid_arg());
+            let last = DATA + REGION_SIZE - size + 4;
+            assert_eq!(syscall(Syscall::IpcSend, 2, last), invalid_arg());
+            // The regions of the other task.
+            add_user_region(2, 0x4000_0000, REGION_SIZE, true);
+            assert_eq!(syscall(Syscall::IpcSend, 2, 0x4000_0000), invalid_arg());
+        });
+        task::get_task_pool().task_switch();
+    });
+}
+
+#[test]
+fn task_rejects_wrapping_slice() {
+    run_kernel(|| {
+        spawn(1, || {
+            add_user_region(1, 0xffff_ff00, 0xff, false);
+            // [0xffff_ff80, 0xffff_ff80 + 0x100) wraps around to 0x80.
+            assert!(!Task::arch_user_access_ok(
+                lookup(1),
+                0xffff_ff80,
+                0x100,
+                false
+            ));
+            assert_eq!(
+                syscall(Syscall::ConsoleWrite, 0xffff_ff80, 0x100),
+                invalid_arg()
+            );
+        });
+        task::get_task_pool().task_switch();
+    });
+}
+
+#[test]
+fn task_rejects_write_to_readonly_region() {
+    run_kernel(|| {
+        spawn(1, || {
+            add_regions(1);
+            assert!(Task::arch_user_access_ok(
+                lookup(1),
+                RODATA,
+                REGION_SIZE,
+                false
+            ));
+            // Receiving writes the message.
+            assert_eq!(syscall(Syscall::IpcRecv, 0, RODATA), invalid_arg());
+            assert_eq!(syscall(Syscall::GetTime, RODATA, 0), invalid_arg());
+        });
+        task::get_task_pool().task_switch();
+    });
+}
@@ -0,0 +1,237 @@
+use crate::arch::timer as arch_timer;
+use crate::config;
+use core::cell::Cell;
+use core::mem;
+use core::num::NonZeroU64;
+use core::slice;
+use klib::list::{self, RemovableLinkedStackOps};
+use klib::{zeroed_array, zeroed_const};
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum TimerKind {
+    // Notifies `Notifications::timer()` to the task and marks the timer as fired.
+    Notify = 0,
+    // Times out the blocking IPC operation of the task.
+    Ipc,
+}
+
+// A timer owned by a task. The deadline is an absolute machine timer value and
+// zero means the timer is not armed. A timer with a non-zero period is re-armed
+// every time it expires.
+#[repr(C)]
+pub struct Timer {
+    link: list::ListLink<'static, Timer>,
+    deadline: Cell<u64>,
+    period: Cell<u32>,
+    tid: Cell<u32>,
+    id: Cell<u32>,
+    kind: Cell<TimerKind>,
+}
+
+impl Timer {
+    pub fn init(&self, tid: u32, id: u32, kind: TimerKind) {
+        self.link.reset();
+        self.deadline.set(0);
+        self.period.set(0);
+        self.tid.set(tid);
+        self.id.set(id);
+        self.kind.set(kind);
+    }
+
+    pub fn tid(&self) -> u32 {
+        self.tid.get()
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id.get()
+    }
+
+    pub fn kind(&self) -> TimerKind {
+        self.kind.get()
+    }
+
+    pub fn deadline(&self) -> u64 {
+        self.deadline.get()
+    }
+
+    pub fn is_armed(&self) -> bool {
+        self.deadline() != 0
+    }
+
+    // Returns the remaining time in milliseconds (rounded up).
+    pub fn remaining_ms(&self) -> u32 {
+        if !self.is_armed() {
+            return 0;
+        }
+        let per_ms = mtime_per_ms();
+        let remaining = self.deadline().saturating_sub(now());
+        ((remaining + per_ms.get() - 1) / per_ms) as u32
+    }
+}
+
+struct TimerQueueTag;
+
+impl list::LinkAdapter<'static, TimerQueueTag> for Timer {
+    fn link(&self) -> &list::ListLink<'static, Timer> {
+        &self.link
+    }
+    fn from_link<'a>(link: &'a list::ListLink<'static, Timer>) -> &'a Timer {
+        // `link` is the first field of `Timer`.
+        unsafe { &*(link as *const list::ListLink<'static, Timer> as *const Timer) }
+    }
+}
+
+// Armed timers sorted by their deadlines. The timer interrupt handler only
+// looks at the front of the queue.
+pub struct TimerQueue {
+    timers: list::ListLink<'static, Timer>,
+    // true while the periodic tick is stopped and the machine timer is
+    // programmed to the earliest deadline instead.
+    tickless: Cell<bool>,
+}
+
+static mut TIMER_QUEUE: TimerQueue = TimerQueue {
+    timers: zeroed_const!(list::ListLink<'static, Timer>),
+    tickless: Cell::new(false),
+};
+
+const NUM_TASK_TIMERS: usize = (config::NUM_TASKS * config::NUM_TIMERS_PER_TASK) as usize;
+
+// Timers set through the timer syscalls: `NUM_TIMERS_PER_TASK` ones per task.
+static mut TASK_TIMERS: [Timer; NUM_TASK_TIMERS] = zeroed_array!(Timer, NUM_TASK_TIMERS);
+
+pub fn get_timer_queue() -> &'static TimerQueue {
+    unsafe { &TIMER_QUEUE }
+}
+
+pub fn task_timers(tid: u32) -> &'static [Timer] {
+    unsafe {
+        slice::from_raw_parts(
+            TASK_TIMERS
+                .as_ptr()
+                .add((tid * config::NUM_TIMERS_PER_TASK) as usize),
+            config::NUM_TIMERS_PER_TASK as usize,
+        )
+    }
+}
+
+impl TimerQueue {
+    fn list(&self) -> list::LinkedList<'_, 'static, Timer, TimerQueueTag> {
+        list::LinkedList::new(&self.timers)
+    }
+
+    // Arms `timer` to expire after `ms` milliseconds and then every `period`
+    // milliseconds if `period` is not zero. An armed timer is rescheduled and
+    // zero `ms` disarms the timer.
+    pub fn add(&self, timer: &'static Timer, ms: u32, period: u32) {
+        self.remove(timer);
+        if ms == 0 {
+            return;
+        }
+
+        timer.period.set(period);
+        self.insert(timer, now() + ms_to_mtime(ms));
+    }
+
+    // Arms `timer` to expire once at the absolute machine timer value `deadline`.
+    pub fn add_at(&self, timer: &'static Timer, deadline: u64) {
+        self.remove(timer);
+        timer.period.set(0);
+        // Zero is reserved for disarmed timers.
+        self.insert(timer, deadline.max(1));
+    }
+
+    fn insert(&self, timer: &'static Timer, deadline: u64) {
+        timer.deadline.set(deadline);
+        let mut list = self.list();
+        match list.iter().find(|t| t.deadline() > deadline) {
+            Some(next) => list.insert_before(next, timer),
+            None => list.push_back(timer),
+        }
+
+        if self.tickless.get() && self.next_deadline() == Some(deadline) {
+            arch_timer::set_oneshot(deadline);
+        }
+    }
+
+    pub fn remove(&self, timer: &'static Timer) {
+        if timer.is_armed() {
+            self.list().remove(timer);
+            timer.deadline.set(0);
+        }
+    }
+
+    pub fn next_deadline(&self) -> Option<u64> {
+        self.list().iter().next().map(|timer| timer.deadline())
+    }
+
+    // Stops the periodic tick if `tickless` is true: the timer interrupt fires
+    // only when the earliest timer expires.
+    pub fn set_tickless(&self, tickless: bool) {
+        self.tickless.set(tickless);
+        if tickless {
+            arch_timer::set_oneshot(self.next_deadline().unwrap_or(u64::MAX));
+        } else {
+            arch_timer::start_tick();
+        }
+    }
+
+    // Dequeues the earliest timer if it has expired at `now`. A periodic timer
+    // is queued again for its next period.
+    pub fn pop_expired(&self, now: u64) -> Option<&'static Timer> {
+        let mut list = self.list();
+        match list.iter().next() {
+            Some(timer) if timer.deadline() <= now => {
+                list.remove(timer);
+                let period = timer.period.get();
+                if period == 0 {
+                    timer.deadline.set(0);
+                } else {
+                    // Skip periods we have missed instead of firing them in a burst.
+                    let next = timer.deadline() + ms_to_mtime(period);
+                    self.insert(
+                        timer,
+                        if next > now {
+                            next
+                        } else {
+                            now + ms_to_mtime(period)
+                        },
+                    );
+                }
+                Some(timer)
+            }
+            _ => None,
+        }
+    }
+}
+
+pub fn now() -> u64 {
+    arch_timer::read_mtime()
+}
+
+pub fn ms_to_mtime(ms: u32) -> u64 {
+    ms as u64 * mtime_per_ms().get()
+}
+
+// Never zero so that dividing by it has no panic path, which the kernel can't
+// link with.
+fn mtime_per_ms() -> NonZeroU64 {
+    NonZeroU64::new((arch_timer::mtime_hz() / 1000) as u64).unwrap_or(NonZeroU64::MIN)
+}
+
+// Never zero: see `mtime_per_ms`.
+fn mtime_hz() -> NonZeroU64 {
+    NonZeroU64::new(arch_timer::mtime_hz() as u64).unwrap_or(NonZeroU64::MIN)
+}
+
+// Converts a machine timer value into microseconds without overflowing the
+// intermediate product.
+pub fn mtime_to_us(mtime: u64) -> u64 {
+    let hz = mtime_hz();
+    (mtime / hz) * 1_000_000 + (mtime % hz) * 1_000_000 / hz
+}
+
+pub fn us_to_mtime(us: u64) -> u64 {
+    let hz = mtime_hz().get();
+    (us / 1_000_000) * hz + (us % 1_000_000) * hz / 1_000_000
+}
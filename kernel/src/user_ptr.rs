@@ -0,0 +1,77 @@
+use crate::arch::task::{ArchTask, Task};
+use crate::task::TaskRef;
+use core::marker::PhantomData;
+use core::{mem, slice};
+use klib::result::KResult;
+
+// A pointer to a `T` passed by a task as a syscall argument. It's checked
+// against the memory regions accessible from the task before the kernel
+// dereferences it.
+pub struct UserPtr<T> {
+    addr: u32,
+    _marker: PhantomData<*mut T>,
+}
+
+impl<T> UserPtr<T> {
+    pub fn new(addr: u32) -> UserPtr<T> {
+        UserPtr {
+            addr,
+            _marker: PhantomData,
+        }
+    }
+
+    fn check(&self, task: TaskRef, write: bool) -> KResult<()> {
+        if self.addr == 0
+            || self.addr as usize % mem::align_of::<T>() != 0
+            || !Task::arch_user_access_ok(task, self.addr, mem::size_of::<T>() as u32, write)
+        {
+            return KResult::InvalidArg;
+        }
+        KResult::Ok(())
+    }
+
+    pub fn as_ref<'a>(&self, task: TaskRef) -> KResult<&'a T> {
+        self.check(task, false)?;
+        KResult::Ok(unsafe { &*(self.addr as *const T) })
+    }
+
+    pub fn as_mut<'a>(&self, task: TaskRef) -> KResult<&'a mut T> {
+        self.check(task, true)?;
+        KResult::Ok(unsafe { &mut *(self.addr as *mut T) })
+    }
+}
+
+// A byte buffer passed by a task as a syscall argument.
+pub struct UserSlice {
+    addr: u32,
+    len: u32,
+}
+
+impl UserSlice {
+    pub fn new(addr: u32, len: u32) -> UserSlice {
+        UserSlice { addr, len }
+    }
+
+    fn check(&self, task: TaskRef, write: bool) -> KResult<()> {
+        if self.addr == 0 || !Task::arch_user_access_ok(task, self.addr, self.len, write) {
+            return KResult::InvalidArg;
+        }
+        KResult::Ok(())
+    }
+
+    pub fn as_slice<'a>(&self, task: TaskRef) -> KResult<&'a [u8]> {
+        if self.len == 0 {
+            return KResult::Ok(&[]);
+        }
+        self.check(task, false)?;
+        KResult::Ok(unsafe { slice::from_raw_parts(self.addr as *const u8, self.len as usize) })
+    }
+
+    pub fn as_mut_slice<'a>(&self, task: TaskRef) -> KResult<&'a mut [u8]> {
+        if self.len == 0 {
+            return KResult::Ok(&mut []);
+        }
+        self.check(task, true)?;
+        KResult::Ok(unsafe { slice::from_raw_parts_mut(self.addr as *mut u8, self.len as usize) })
+    }
+}
@@ -3,7 +3,19 @@ pub mod cramp32 {
     pub mod mmio;
 }
 
-#[cfg(not(all(target_arch = "riscv32", feature = "cramp32")))]
+#[cfg(all(
+    not(all(target_arch = "riscv32", feature = "cramp32")),
+    any(test, feature = "hosted")
+))]
+pub mod hosted {
+    pub mod mmio;
+}
+
+#[cfg(not(any(
+    all(target_arch = "riscv32", feature = "cramp32"),
+    test,
+    feature = "hosted"
+)))]
 pub mod unsupported {
     pub mod mmio;
 }
@@ -0,0 +1,27 @@
+// On the host, there is no linker script to define the symbols, so the tests
+// define the ones the code under test looks up, e.g. an entry point returned by
+// `System::register_entry` in the hosted syscall backend. Symbols are shared
+// by all the tests in the process, so they must define the same addresses.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+static SYMBOLS: Mutex<BTreeMap<&'static str, u32>> = Mutex::new(BTreeMap::new());
+
+pub fn define_symbol(symbol: &'static str, address: u32) {
+    SYMBOLS.lock().unwrap().insert(symbol, address);
+}
+
+pub fn address_of(symbol: &str) -> u32 {
+    match SYMBOLS.lock().unwrap().get(symbol) {
+        Some(address) => *address,
+        None => panic!("undefined symbol: {}", symbol),
+    }
+}
+
+#[macro_export]
+macro_rules! local_address_of {
+    ($symbol: expr) => {
+        $crate::arch::hosted::mmio::address_of($symbol)
+    };
+}
@@ -14,10 +14,8 @@ impl<'a> BufWriter<'a> {
 
     pub fn as_slice(&self) -> &[u8] {
         unsafe {
-            &*ptr::slice_from_raw_parts(
-                mem::MaybeUninit::slice_assume_init_ref(self.buf).as_ptr(),
-                self.pos,
-            )
+            // The first `pos` bytes have been written.
+            &*ptr::slice_from_raw_parts(self.buf.as_ptr().cast::<u8>(), self.pos)
         }
     }
 }
@@ -1,11 +1,25 @@
+#[cfg(all(target_arch = "riscv32", not(feature = "qemu-virt")))]
 use crate::mmio::readv;
+#[cfg(target_arch = "riscv32")]
 use core::arch::asm;
+#[cfg(not(target_arch = "riscv32"))]
+use std::sync::OnceLock;
+#[cfg(not(target_arch = "riscv32"))]
+use std::time::Instant;
 
+#[cfg(all(target_arch = "riscv32", not(feature = "qemu-virt")))]
 const REG_CONFIG_CLOCK_HZ: *mut u32 = 0x4000_0004 as *mut u32;
+// QEMU has no configuration registers. Without -icount, rdcycle counts host
+// ticks, so this is only good for rough delays.
+#[cfg(all(target_arch = "riscv32", feature = "qemu-virt"))]
+const QEMU_CLOCK_HZ: u32 = 1_000_000_000;
+// On the host, a cycle is a nanosecond since the first read.
+#[cfg(not(target_arch = "riscv32"))]
+const HOSTED_CLOCK_HZ: u32 = 1_000_000_000;
 static mut CLOCK_HZ: u32 = 0;
 
-#[allow(dead_code)]
-fn read_cycle() -> u64 {
+#[cfg(target_arch = "riscv32")]
+pub fn read_cycle() -> u64 {
     let mut l: u32;
     let mut h: u32;
     let mut hv: u32;
@@ -22,6 +36,12 @@ fn read_cycle() -> u64 {
     ((h as u64) << 32) | (l as u64)
 }
 
+#[cfg(not(target_arch = "riscv32"))]
+pub fn read_cycle() -> u64 {
+    static START: OnceLock<Instant> = OnceLock::new();
+    START.get_or_init(Instant::now).elapsed().as_nanos() as u64
+}
+
 #[allow(dead_code)]
 pub fn clock_hz() -> u32 {
     unsafe { CLOCK_HZ }
@@ -33,8 +53,23 @@ pub fn wait(cycles: u32) {
     while read_cycle() - start < (cycles as u64) {}
 }
 
+#[cfg(all(target_arch = "riscv32", not(feature = "qemu-virt")))]
 pub fn init() {
     unsafe {
         CLOCK_HZ = readv(REG_CONFIG_CLOCK_HZ);
     }
 }
+
+#[cfg(all(target_arch = "riscv32", feature = "qemu-virt"))]
+pub fn init() {
+    unsafe {
+        CLOCK_HZ = QEMU_CLOCK_HZ;
+    }
+}
+
+#[cfg(not(target_arch = "riscv32"))]
+pub fn init() {
+    unsafe {
+        CLOCK_HZ = HOSTED_CLOCK_HZ;
+    }
+}
@@ -96,6 +96,14 @@ impl Display for usize {
     }
 }
 
+impl Display for &[u8] {
+    fn fmt(&self, writer: &mut dyn Write) {
+        for &ch in self.iter() {
+            writer.write_char(ch);
+        }
+    }
+}
+
 #[macro_export]
 macro_rules! make_args {
     ($arg1:expr $(,$args:expr)*) => {
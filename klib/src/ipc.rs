@@ -1,5 +1,6 @@
 use core::mem;
 use core::ops::BitOr;
+use core::ptr;
 
 #[derive(Clone, Copy)]
 pub struct IpcFlags(u8);
@@ -7,7 +8,7 @@ pub struct IpcFlags(u8);
 #[allow(unused)]
 impl IpcFlags {
     const NOBLOCK: u8 = 1 << 0;
-    // const KERNEL: u8 = 1 << 1; // Internally used by kernel.
+    const KERNEL: u8 = 1 << 1; // Internally used by kernel.
 
     pub fn from_u32(flags: u32) -> IpcFlags {
         IpcFlags(flags as u8)
@@ -25,6 +26,13 @@ impl IpcFlags {
     pub fn is_noblock(&self) -> bool {
         self.0 & Self::NOBLOCK != 0
     }
+    // The message is sent by the kernel on behalf of the current task.
+    pub fn kernel() -> IpcFlags {
+        IpcFlags(Self::KERNEL)
+    }
+    pub fn is_kernel(&self) -> bool {
+        self.0 & Self::KERNEL != 0
+    }
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -32,26 +40,86 @@ pub struct MessageType(pub u32);
 
 impl MessageType {
     pub const NOTIFICATIONS: MessageType = MessageType(1);
+    // Sent by the kernel to the pager of a task which caused a CPU exception.
+    // Picked from the top of the range so that it won't collide with the
+    // message types defined by servers.
+    pub const EXCEPTION: MessageType = MessageType(0x8000_0001);
 }
 
 #[derive(Clone, Copy)]
 pub struct Message {
     pub message_type: MessageType,
     pub src_tid: u32,
+    // An out-of-line payload copied by the kernel to the receiver. In a message
+    // to send, the bytes to send. In a message to receive into, the buffer
+    // designated by the receiver and its capacity. A received message points to
+    // the bytes received into that buffer. The send fails with
+    // `KResult::TooLarge` if they don't fit.
+    pub ool_ptr: usize,
+    pub ool_len: usize,
     pub raw: [u8; 24],
 }
 
 impl Message {
+    // A message with no type, no payload and no out-of-line buffer. Fill in
+    // the fields with the struct update syntax.
+    pub const fn zeroed() -> Message {
+        Message {
+            message_type: MessageType(0),
+            src_tid: 0,
+            ool_ptr: 0,
+            ool_len: 0,
+            raw: [0; 24],
+        }
+    }
+
     pub fn set_payload<T: Copy>(&mut self, data: &T) {
         unsafe {
             *mem::transmute::<_, &mut _>(&mut self.raw) = *data;
         }
     }
+
+    // `data` must live until the message is sent.
+    pub fn set_ool(&mut self, data: &[u8]) {
+        self.ool_ptr = data.as_ptr() as usize;
+        self.ool_len = data.len();
+    }
 }
 
 #[derive(Clone, Copy)]
 pub struct Notifications(u32);
 
+// The payload of a `MessageType::EXCEPTION` message.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct ExceptionPayload {
+    // The faulting task. It stays suspended until its pager destroys it.
+    pub tid: u32,
+    // `mcause`, `mtval` and `mepc` on the exception.
+    pub cause: u32,
+    pub tval: u32,
+    pub pc: u32,
+}
+
+// The payload of a `MessageType::NOTIFICATIONS` message.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct NotificationPayload {
+    pub notifications: Notifications,
+    // A bitmap of the timer ids fired since the last notification message.
+    pub fired_timers: u32,
+}
+
+impl NotificationPayload {
+    // Returns `None` if `message` is not a `MessageType::NOTIFICATIONS` one.
+    pub fn parse(message: &Message) -> Option<NotificationPayload> {
+        if message.message_type != MessageType::NOTIFICATIONS {
+            return None;
+        }
+        Some(unsafe { ptr::read_unaligned(message.raw.as_ptr() as *const NotificationPayload) })
+    }
+}
+
 #[allow(unused)]
 impl Notifications {
     const TIMER: u32 = 1 << 0;
@@ -62,10 +130,16 @@ impl Notifications {
     pub fn from_u32(n: u32) -> Notifications {
         Notifications(n)
     }
+    pub fn as_u32(&self) -> u32 {
+        self.0
+    }
 
     pub fn timer() -> Notifications {
         Notifications(Self::TIMER)
     }
+    pub fn irq() -> Notifications {
+        Notifications(Self::IRQ)
+    }
     pub fn aborted() -> Notifications {
         Notifications(Self::ABORTED)
     }
@@ -81,6 +155,9 @@ impl Notifications {
     pub fn is_timer(&self) -> bool {
         self.0 & Self::TIMER != 0
     }
+    pub fn is_irq(&self) -> bool {
+        self.0 & Self::IRQ != 0
+    }
     pub fn exists(&self) -> bool {
         self.0 != 0
     }
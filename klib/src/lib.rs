@@ -1,11 +1,12 @@
-#![no_std]
+#![cfg_attr(not(any(test, feature = "hosted")), no_std)]
 #![feature(try_trait_v2)]
+#![feature(try_trait_v2_residual)]
 #![feature(maybe_uninit_slice)]
 #![feature(ptr_sub_ptr)]
 
 pub mod arch;
 pub mod buf_writer;
-#[cfg(target_arch = "riscv32")]
+#[cfg(any(target_arch = "riscv32", test, feature = "hosted"))]
 pub mod cycle;
 pub mod fmt;
 pub mod ipc;
@@ -13,3 +14,5 @@ pub mod list;
 pub mod mmio;
 pub mod result;
 pub mod syscall;
+pub mod task;
+pub mod time;
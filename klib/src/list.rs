@@ -228,6 +228,19 @@ where
         }
     }
 
+    // Links `elem` in front of `pos`, which must be in this list.
+    pub fn insert_before(&mut self, pos: &'s T, elem: &'s T) {
+        let prev = pos.link().prev.get();
+        elem.link().next.set(Some(pos.link()));
+        elem.link().prev.set(prev);
+        if let Some(prev) = prev {
+            prev.next.set(Some(elem.link()));
+        } else {
+            self.link_start.next.set(Some(elem.link()));
+        }
+        pos.link().prev.set(Some(elem.link()));
+    }
+
     pub fn iter(&self) -> ListIterator<'_, 's, T, LinkTag> {
         ListIterator {
             current: self.link_start,
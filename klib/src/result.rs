@@ -1,5 +1,5 @@
 use core::convert;
-use core::mem::transmute_copy;
+use core::mem::MaybeUninit;
 use core::ops::{self, ControlFlow};
 
 #[repr(u32)]
@@ -56,7 +56,13 @@ impl<T> KResult<T> {
         // if e == 0 {
         //     kpanic!(b"err_from_u32 called for ok");
         // }
-        unsafe { transmute_copy(&*(<*const _>::from(&e).cast::<Self>())) }
+        // `#[repr(u32)]` puts the discriminant first, and the error variants
+        // carry no data.
+        let mut result = MaybeUninit::<Self>::uninit();
+        unsafe {
+            result.as_mut_ptr().cast::<u32>().write(e);
+            result.assume_init()
+        }
     }
 
     pub fn map<U, F: FnOnce(T) -> U>(self, op: F) -> KResult<U> {
@@ -99,6 +105,11 @@ impl<T> ops::Try for KResult<T> {
     }
 }
 
+// Required of `Try::Residual` by newer toolchains.
+impl<T> ops::Residual<T> for KResult<convert::Infallible> {
+    type TryType = KResult<T>;
+}
+
 impl<T> ops::FromResidual<KResult<convert::Infallible>> for KResult<T> {
     #[inline]
     #[track_caller]
@@ -17,6 +17,26 @@ pub enum Syscall {
     ScheduleTask,
     IrqAquire,
     IrqRelease,
+    TaskInfo,
+    IrqAck,
+    IpcRecvNoblock,
+    /// The `*Timeout` variants give up after the timeout (in milliseconds) in
+    /// the last argument with `KResult::TryAgain`. Zero means no timeout: they
+    /// block as `IpcRecv`, `IpcSend` and `IpcCall` do. Use `IpcRecvNoblock`
+    /// and `IpcSendNoblock` not to block.
+    IpcRecvTimeout,
+    /// See `IpcRecvTimeout`.
+    IpcSendTimeout,
+    /// See `IpcRecvTimeout`. The timeout applies to each of the send and the
+    /// receive phases.
+    IpcCallTimeout,
+    IpcAbort,
+    TimerSet,
+    TimerCancel,
+    GetTime,
+    TimerSetUntil,
+    CreateTaskWithStack,
+    IpcReply,
 }
 
 impl Syscall {
@@ -0,0 +1,51 @@
+use crate::ipc::Notifications;
+
+pub const TASK_PRIORITY_MAX: u32 = 8;
+// `TaskInfo::dst_tid` of a task not waiting in any sender queue. 0 can't be
+// used since it's the TID of the idle task.
+pub const NOT_SENDING: u32 = u32::MAX;
+
+#[repr(u32)]
+#[derive(PartialEq, Clone, Copy)]
+pub enum TaskState {
+    Unused = 0,
+    Runnable,
+    Blocked,
+}
+
+#[repr(u32)]
+#[derive(PartialEq, Clone, Copy)]
+pub enum TaskType {
+    Idle = 0,
+    User,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct TaskInfo {
+    pub tid: u32,
+    pub state: TaskState,
+    pub task_type: TaskType,
+    // The priority the task runs at, raised above `base_priority` while
+    // higher-priority tasks wait for it in IPC.
+    pub priority: u32,
+    pub time_slice: i32,
+    pub quantum: i32,
+    pub notifications: Notifications,
+    // The task from which the task is waiting for a message, when blocked in receiving.
+    pub src_tid: u32,
+    // The task in whose sender queue the task is waiting, when blocked in
+    // sending, or `NOT_SENDING`.
+    pub dst_tid: u32,
+    pub timeout: u32,
+    // The task notified of CPU exceptions in the task, or 0 if none.
+    pub pager: u32,
+    // The maximum number of bytes ever used in the kernel stack of the task.
+    pub kernel_stack_peak: u32,
+    // The size of the stack registered by `CreateTaskWithStack`, or 0 if none.
+    pub stack_size: u32,
+    // The maximum number of bytes ever used in the registered stack.
+    pub stack_peak: u32,
+    // The priority set by `ScheduleTask`.
+    pub base_priority: u32,
+}
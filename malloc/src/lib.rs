@@ -1,4 +1,4 @@
-#![no_std]
+#![cfg_attr(not(any(test, feature = "hosted")), no_std)]
 #![feature(concat_bytes)]
 #![feature(maybe_uninit_slice)]
 #![feature(asm_const)]
@@ -18,10 +18,12 @@ pub mod malloc;
 
 #[cfg(test)]
 mod bit_trie_test;
+#[cfg(test)]
+mod malloc_test;
 
-#[cfg(not(test))]
+#[cfg(not(any(test, feature = "hosted")))]
 use core::panic::PanicInfo;
-#[cfg(not(test))]
+#[cfg(not(any(test, feature = "hosted")))]
 #[panic_handler]
 fn panic(_info: &PanicInfo) -> ! {
     loop {}
@@ -5,9 +5,12 @@ use core::cell::Cell;
 use core::mem;
 use core::ptr;
 use ipc::malloc;
+use klib::ipc::Message;
 use klib::list::{self, RemovableLinkedStackOps};
+#[cfg(not(any(test, feature = "hosted")))]
+use klib::local_address_of;
 use klib::result::KResult;
-use klib::{local_address_of, zeroed_array};
+use klib::zeroed_array;
 
 #[no_mangle]
 pub extern "C" fn malloc_task() {
@@ -16,25 +19,40 @@ pub extern "C" fn malloc_task() {
 
     loop {
         match syscall::ipc_recv(0) {
-            KResult::Ok(message) => match message.message_type {
-                malloc::ALLOC_MESSAGE => {
-                    let payload = malloc::AllocMessage::parse_request(&message);
-                    let result = allocator.alloc(payload.size, payload.align, message.src_tid);
-                    let ptr = result.unwrap_or(ptr::null_mut());
-                    syscall::ipc_send(message.src_tid, &malloc::AllocMessage::response(ptr));
-                }
-                malloc::DEALLOC_MESSAGE => {
-                    let ptr = malloc::DeallocMessage::parse_request(&message);
-                    allocator.dealloc(ptr, message.src_tid);
-                    syscall::ipc_send(message.src_tid, &malloc::DeallocMessage::response());
-                }
-                _ => print_error!(b"unknown message type: {}\n", message.message_type.0),
+            KResult::Ok(message) => match handle_message(allocator, &message) {
+                // Fails without blocking if the client has gone away.
+                KResult::Ok(reply) => match syscall::ipc_reply(message.src_tid, &reply) {
+                    KResult::Ok(()) => {}
+                    err => print_error!(b"ipc_reply failed: {}\n", err.err_as_u32()),
+                },
+                KResult::DontReply => {}
+                err => print_error!(b"malloc failed: {}\n", err.err_as_u32()),
             },
             err => print_error!(b"ipc_recv failed: {}\n", err.err_as_u32()),
         };
     }
 }
 
+fn handle_message(allocator: &HeapAllocator, message: &Message) -> KResult<Message> {
+    match message.message_type {
+        malloc::ALLOC_MESSAGE => {
+            let payload = malloc::AllocMessage::parse_request(message);
+            let result = allocator.alloc(payload.size, payload.align, message.src_tid);
+            let ptr = result.unwrap_or(ptr::null_mut());
+            KResult::Ok(malloc::AllocMessage::response(ptr))
+        }
+        malloc::DEALLOC_MESSAGE => {
+            let ptr = malloc::DeallocMessage::parse_request(message);
+            allocator.dealloc(ptr, message.src_tid);
+            KResult::Ok(malloc::DeallocMessage::response())
+        }
+        _ => {
+            print_error!(b"unknown message type: {}\n", message.message_type.0);
+            KResult::DontReply
+        }
+    }
+}
+
 #[repr(C)]
 struct HeapAllocator {
     brk: Cell<*mut u32>,
@@ -46,22 +64,24 @@ struct HeapAllocator {
 
 static mut HEAP_ALLOCATOR: HeapAllocator = HeapAllocator::zeroed();
 
+// A word of the heap, even where pointers are wider (on the host).
 #[derive(Clone, Copy)]
-struct SizeField(usize);
+struct SizeField(u32);
 
 impl SizeField {
-    const ALLOCATED_BIT: usize = 2;
-    const PREV_CHUNK_FREE_BIT: usize = 1;
+    const ALLOCATED_BIT: u32 = 2;
+    const PREV_CHUNK_FREE_BIT: u32 = 1;
     const WORD_SIZE: usize = 4;
 
     fn with_size_word(&self, size: usize) -> Self {
         SizeField(
-            (self.0 & (Self::ALLOCATED_BIT | Self::PREV_CHUNK_FREE_BIT)) | (size * Self::WORD_SIZE),
+            (self.0 & (Self::ALLOCATED_BIT | Self::PREV_CHUNK_FREE_BIT))
+                | (size * Self::WORD_SIZE) as u32,
         )
     }
 
     fn size_word(&self) -> usize {
-        self.0 / Self::WORD_SIZE
+        self.0 as usize / Self::WORD_SIZE
     }
 
     fn with_allocated(&self) -> Self {
@@ -147,7 +167,8 @@ impl FreeChunk {
     }
 
     fn reset_with_prev_chunk_deallocated(&self, size_word: usize) {
-        self.size.update(|_| SizeField(size_word * WORD_SIZE));
+        self.size
+            .update(|_| SizeField((size_word * WORD_SIZE) as u32));
         self.set_chunk_end_size(size_word);
         self.init_chunk_end_allocated_bit(size_word);
     }
@@ -246,6 +267,22 @@ const fn alloc_chunk_data_offset_word() -> usize {
     mem::offset_of!(Chunk, data) / WORD_SIZE
 }
 
+// The heap follows the image (see sections.ld).
+#[cfg(not(any(test, feature = "hosted")))]
+fn heap_start() -> *mut u32 {
+    let heap_start: u32 = local_address_of!("__heap_start");
+    heap_start as *mut u32
+}
+
+// On the host, the heap is a static buffer. The first chunk starts at the word
+// below the heap start, which is aligned to 8 bytes for the links of the chunks
+// (pointers are 8 bytes on the host).
+#[cfg(any(test, feature = "hosted"))]
+fn heap_start() -> *mut u32 {
+    static mut HEAP: [u64; 0x4000] = [0; 0x4000];
+    unsafe { (ptr::addr_of_mut!(HEAP) as *mut u32).add(1) }
+}
+
 const WORD_SIZE: usize = HeapAllocator::WORD_SIZE;
 const LARGE_CHUNK_MIN_SIZE_WORD: usize = HeapAllocator::LARGE_CHUNK_MIN_SIZE_WORD;
 
@@ -312,6 +349,8 @@ impl HeapAllocator {
     const WORD_SIZE: usize = 4;
     const CHUNK_SIZE_WORD: usize = mem::size_of::<Chunk>() / Self::WORD_SIZE;
     const MIN_CHUNK_SIZE_WORD: usize = Self::CHUNK_SIZE_WORD + 12;
+    // The header of an allocated chunk and 12 bytes of data: 6 words on rv32.
+    const SMALL_CHUNK_MIN_SIZE_WORD: usize = (alloc_chunk_data_offset_word() + 3 + 1) & !1;
     const NUM_SMALL_CHUNKS: usize = 32;
     const NUM_LARGE_CHUNKS: usize = 20;
     const LARGE_CHUNK_MIN_SIZE_WORD: usize =
@@ -337,8 +376,7 @@ impl HeapAllocator {
     }
 
     fn init(&self) {
-        let heap_start: u32 = local_address_of!("__heap_start");
-        let brk = unsafe { (heap_start as *mut u32).sub(1) };
+        let brk = unsafe { heap_start().sub(1) };
         unsafe { *brk = 0 };
         self.brk.set(brk);
     }
@@ -351,20 +389,17 @@ impl HeapAllocator {
         }
     }
 
+    // Chunks have an even number of words to keep the data aligned to
+    // MIN_ALIGN.
     fn small_req_size_to_size_word(size: usize) -> usize {
-        if size <= 4 {
-            6
-        } else {
-            6 + ((size - 5) >> 3 << 1)
-        }
+        let size_word =
+            (alloc_chunk_data_offset_word() + (size + Self::WORD_SIZE - 1) / Self::WORD_SIZE + 1)
+                & !1;
+        size_word.max(Self::SMALL_CHUNK_MIN_SIZE_WORD)
     }
 
     fn small_chunk_size_word_to_index(size_word: usize) -> usize {
-        if size_word <= 6 {
-            0
-        } else {
-            (size_word - 5) >> 1
-        }
+        (size_word - Self::SMALL_CHUNK_MIN_SIZE_WORD) >> 1
     }
 
     fn alloc_unaligned(&self, size: usize, tid: u32) -> KResult<*mut u8> {
@@ -0,0 +1,51 @@
+use crate::malloc::malloc_task;
+use ipc::malloc::{AllocMessage, DeallocMessage};
+use ipc::tid::MALLOC_TASK_TID;
+use syscall::hosted::System;
+use syscall::syscall::ipc_call;
+
+fn alloc(size: usize, align: usize) -> *mut u8 {
+    let response = ipc_call(MALLOC_TASK_TID, &mut AllocMessage::request(size, align));
+    AllocMessage::parse_response(&response.ok().unwrap())
+}
+
+fn dealloc(ptr: *mut u8) {
+    assert!(ipc_call(MALLOC_TASK_TID, &mut DeallocMessage::request(ptr)).is_ok());
+}
+
+// The allocator is a static, so there is a single test running the server.
+#[test]
+fn hosted_malloc_task() {
+    let system = System::new();
+    system
+        .spawn_task(MALLOC_TASK_TID, || malloc_task())
+        .ok()
+        .unwrap();
+    system.run_as_task(4, || {
+        let sizes = [1, 4, 12, 13, 40, 100];
+        let ptrs = sizes.map(|size| alloc(size, 8));
+        for (i, (&ptr, &size)) in ptrs.iter().zip(sizes.iter()).enumerate() {
+            assert!(!ptr.is_null());
+            assert_eq!(ptr as usize % 8, 0);
+            unsafe { ptr.write_bytes(i as u8, size) };
+        }
+        // The chunks do not overlap.
+        for (i, (&ptr, &size)) in ptrs.iter().zip(sizes.iter()).enumerate() {
+            let data = unsafe { core::slice::from_raw_parts(ptr, size) };
+            assert!(data.iter().all(|&b| b == i as u8));
+        }
+
+        // A freed chunk is reused for a request of the same size.
+        dealloc(ptrs[4]);
+        assert_eq!(alloc(40, 8), ptrs[4]);
+
+        // Freed neighbours are combined into a chunk for a larger request.
+        dealloc(ptrs[1]);
+        dealloc(ptrs[2]);
+        dealloc(ptrs[3]);
+        assert_eq!(alloc(40, 8), ptrs[1]);
+
+        // Larger alignments are not supported.
+        assert!(alloc(8, 16).is_null());
+    });
+}
@@ -0,0 +1,45 @@
+#!/usr/bin/env ruby
+
+# Builds the contents of the .symbols section from the output of
+#
+#     llvm-nm --defined-only --demangle kernel.elf.fat
+#
+# The layout (little endian) is read by kernel/src/symbols.rs:
+#
+#     header:  magic "SYMS", the number of symbols (u32)
+#     symbols: address, offset of the name in the strings (u32 each),
+#              sorted by the address
+#     strings: NUL-terminated names
+#
+# The output is padded to SIZE bytes, the size of .symbols in sections.ld.
+#
+# Usage: symbol_table.rb SIZE < nm_output > symbols.bin
+
+size = Integer(ARGV.shift)
+
+symbols = {}
+while line = ARGF.gets
+    _, addr, name = /^([0-9a-f]+)\s+[tTwW]\s+(.+)$/i.match(line.chomp).to_a
+    next if addr == nil
+    # Skip mapping symbols and local labels.
+    next if name.start_with?("$", ".L")
+    # Drop the hash suffix of the legacy Rust mangling.
+    name = name.sub(/::h[0-9a-f]{16}$/, "")
+    symbols[addr.to_i(16)] ||= name
+end
+
+strings = "".b
+entries = []
+symbols.sort.each do |addr, name|
+    entries << addr << strings.bytesize
+    strings << name.b << "\0"
+end
+
+table = ["SYMS", symbols.size].pack("a4V") + entries.pack("V*") + strings
+if table.bytesize > size then
+    STDERR.puts "symbol table is too large (#{table.bytesize} > #{size} bytes): enlarge .symbols in sections.ld"
+    exit 1
+end
+
+STDOUT.binmode
+STDOUT.write(table + "\0" * (size - table.bytesize))
@@ -6,10 +6,33 @@ pub mod cramp32 {
 #[cfg(all(target_arch = "riscv32", feature = "cramp32"))]
 pub use cramp32::syscall;
 
-#[cfg(not(all(target_arch = "riscv32", feature = "cramp32")))]
+#[cfg(all(
+    not(all(target_arch = "riscv32", feature = "cramp32")),
+    any(test, feature = "hosted")
+))]
+pub mod hosted {
+    pub mod sim;
+    pub mod syscall;
+}
+
+#[cfg(all(
+    not(all(target_arch = "riscv32", feature = "cramp32")),
+    any(test, feature = "hosted")
+))]
+pub use hosted::syscall;
+
+#[cfg(not(any(
+    all(target_arch = "riscv32", feature = "cramp32"),
+    test,
+    feature = "hosted"
+)))]
 pub mod unsupported {
     pub mod syscall;
 }
 
-#[cfg(not(all(target_arch = "riscv32", feature = "cramp32")))]
+#[cfg(not(any(
+    all(target_arch = "riscv32", feature = "cramp32"),
+    test,
+    feature = "hosted"
+)))]
 pub use unsupported::syscall;
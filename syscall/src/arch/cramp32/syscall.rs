@@ -1,6 +1,8 @@
 use ::klib::ipc::Message;
 use ::klib::result::KResult;
 use ::klib::syscall::Syscall;
+use ::klib::task::TaskInfo;
+use ::klib::time::Instant;
 use core::arch::asm;
 use core::mem;
 
@@ -89,38 +91,177 @@ pub fn set_timer(timeout: u32) -> KResult<()> {
     syscall1(Syscall::SetTimer, timeout)
 }
 
+pub fn timer_set(id: u32, ms: u32, period: u32) -> KResult<()> {
+    syscall3(Syscall::TimerSet, id, ms, period)
+}
+
+pub fn timer_cancel(id: u32) -> KResult<()> {
+    syscall1(Syscall::TimerCancel, id)
+}
+
+pub fn timer_set_until(id: u32, deadline: Instant) -> KResult<()> {
+    let micros = deadline.as_micros();
+    syscall3(
+        Syscall::TimerSetUntil,
+        id,
+        micros as u32,
+        (micros >> 32) as u32,
+    )
+}
+
+pub fn get_time() -> KResult<Instant> {
+    let mut micros: u64 = 0;
+    syscall1(Syscall::GetTime, unsafe {
+        mem::transmute(<*mut _>::from(&mut micros))
+    })
+    .map(|_| Instant::from_micros(micros))
+}
+
 pub fn console_write(s: &[u8]) -> KResult<()> {
     syscall2(Syscall::ConsoleWrite, s.as_ptr() as u32, s.len() as u32)
 }
 
+// The message designates no out-of-line buffer: it's zeroed.
 pub fn ipc_recv(src_tid: u32) -> KResult<Message> {
-    let mut message: mem::MaybeUninit<Message> = mem::MaybeUninit::uninit();
+    let mut message = Message::zeroed();
     syscall2(Syscall::IpcRecv, src_tid, unsafe {
         mem::transmute(<*mut _>::from(&mut message))
     })
-    .map(|_| unsafe { message.assume_init() })
+    .map(|_| message)
 }
 
-pub fn ipc_send(dst_tid: u32, message: &Message) -> KResult<()> {
+pub fn ipc_recv_noblock(src_tid: u32) -> KResult<Message> {
+    let mut message = Message::zeroed();
+    syscall2(Syscall::IpcRecvNoblock, src_tid, unsafe {
+        mem::transmute(<*mut _>::from(&mut message))
+    })
+    .map(|_| message)
+}
+
+pub fn ipc_recv_timeout(src_tid: u32, timeout: u32) -> KResult<Message> {
+    let mut message = Message::zeroed();
+    syscall3(
+        Syscall::IpcRecvTimeout,
+        src_tid,
+        unsafe { mem::transmute(<*mut _>::from(&mut message)) },
+        timeout,
+    )
+    .map(|_| message)
+}
+
+pub fn ipc_recv_ool(src_tid: u32, buffer: &mut [u8]) -> KResult<Message> {
+    let mut message = Message::zeroed();
+    message.ool_ptr = buffer.as_mut_ptr() as usize;
+    message.ool_len = buffer.len();
+    syscall2(Syscall::IpcRecv, src_tid, unsafe {
+        mem::transmute(<*mut _>::from(&mut message))
+    })
+    .map(|_| message)
+}
+
+pub fn ipc_send(dst_tid: u32, message: &mut Message) -> KResult<()> {
     syscall2(Syscall::IpcSend, dst_tid, unsafe {
-        mem::transmute(<*const _>::from(message))
+        mem::transmute(<*mut _>::from(message))
     })
 }
 
-pub fn ipc_call(dst_tid: u32, message: &Message) -> KResult<Message> {
+pub fn ipc_call(dst_tid: u32, message: &mut Message) -> KResult<Message> {
     let mut ipc_message: Message = *message;
-    syscall2(Syscall::IpcCall, dst_tid, unsafe {
+    let result = syscall2(Syscall::IpcCall, dst_tid, unsafe {
         mem::transmute(<*mut _>::from(&mut ipc_message))
-    })
-    .map(|_| ipc_message)
+    });
+    if let KResult::TooLarge = result {
+        message.ool_len = ipc_message.ool_len;
+    }
+    result.map(|_| ipc_message)
 }
 
-pub fn ipc_send_noblock(dst_tid: u32, message: &Message) -> KResult<()> {
+pub fn ipc_send_noblock(dst_tid: u32, message: &mut Message) -> KResult<()> {
     syscall2(Syscall::IpcSendNoblock, dst_tid, unsafe {
+        mem::transmute(<*mut _>::from(message))
+    })
+}
+
+pub fn ipc_send_timeout(dst_tid: u32, message: &mut Message, timeout: u32) -> KResult<()> {
+    syscall3(
+        Syscall::IpcSendTimeout,
+        dst_tid,
+        unsafe { mem::transmute(<*mut _>::from(message)) },
+        timeout,
+    )
+}
+
+pub fn ipc_call_timeout(dst_tid: u32, message: &mut Message, timeout: u32) -> KResult<Message> {
+    let mut ipc_message: Message = *message;
+    let result = syscall3(
+        Syscall::IpcCallTimeout,
+        dst_tid,
+        unsafe { mem::transmute(<*mut _>::from(&mut ipc_message)) },
+        timeout,
+    );
+    if let KResult::TooLarge = result {
+        message.ool_len = ipc_message.ool_len;
+    }
+    result.map(|_| ipc_message)
+}
+
+pub fn ipc_reply(dst_tid: u32, message: &Message) -> KResult<()> {
+    syscall2(Syscall::IpcReply, dst_tid, unsafe {
         mem::transmute(<*const _>::from(message))
     })
 }
 
+pub fn ipc_abort(tid: u32) -> KResult<()> {
+    syscall1(Syscall::IpcAbort, tid)
+}
+
 pub fn create_task(tid: u32, pc: u32, sp: u32) -> KResult<()> {
     syscall3(Syscall::CreateTask, tid, pc, sp)
 }
+
+pub fn create_task_with_stack(tid: u32, pc: u32, stack: &'static mut [u8]) -> KResult<()> {
+    syscall4(
+        Syscall::CreateTaskWithStack,
+        tid,
+        pc,
+        stack.as_mut_ptr() as u32,
+        stack.len() as u32,
+    )
+}
+
+pub fn destroy_task(tid: u32) -> KResult<()> {
+    syscall1(Syscall::DestroyTask, tid)
+}
+
+pub fn exit_task() -> ! {
+    let _ = syscall0(Syscall::ExitTask);
+    loop {}
+}
+
+pub fn task_self() -> KResult<u32> {
+    syscall0r(Syscall::TaskSelf)
+}
+
+pub fn task_info(tid: u32) -> KResult<TaskInfo> {
+    let mut info: mem::MaybeUninit<TaskInfo> = mem::MaybeUninit::uninit();
+    syscall2(Syscall::TaskInfo, tid, unsafe {
+        mem::transmute(<*mut _>::from(&mut info))
+    })
+    .map(|_| unsafe { info.assume_init() })
+}
+
+pub fn schedule_task(tid: u32, priority: u32, time_slice: u32) -> KResult<()> {
+    syscall3(Syscall::ScheduleTask, tid, priority, time_slice)
+}
+
+pub fn irq_acquire(irq: u32) -> KResult<()> {
+    syscall1(Syscall::IrqAquire, irq)
+}
+
+pub fn irq_release(irq: u32) -> KResult<()> {
+    syscall1(Syscall::IrqRelease, irq)
+}
+
+pub fn irq_ack(irq: u32) -> KResult<()> {
+    syscall1(Syscall::IrqAck, irq)
+}
@@ -0,0 +1,910 @@
+// A hosted simulation of the kernel to run user-space tasks on the host: each
+// task is an OS thread, and the syscalls are serviced by `Kernel`, which
+// follows the rendezvous IPC in kernel/src/ipc.rs. Keep the two in sync: the
+// results of the syscalls, the order in which the queued senders are received
+// and the delivery of notifications are the same as in the kernel.
+//
+// The following is not simulated, so tests must not depend on it:
+//
+// - Scheduling. Tasks run concurrently, so the priorities and the time slices
+//   set by `schedule_task` are only recorded, and the priority inheritance in
+//   IPC (kernel/src/task.rs) does not happen.
+// - The direct switch to the receiver on send and call: which task runs next
+//   is up to the host.
+// - Memory protection: pointers passed to syscalls are not validated against
+//   the regions of the task, and the stacks passed to `create_task_with_stack`
+//   are not used.
+// - Interrupts and CPU exceptions: there are no devices (tests deliver
+//   interrupts with `System::notify`), and a task which panics exits instead
+//   of being reported to its pager.
+
+use klib::ipc::{IpcFlags, Message, MessageType, NotificationPayload, Notifications};
+use klib::result::KResult;
+use klib::task::{TaskInfo, TaskState, TaskType, NOT_SENDING, TASK_PRIORITY_MAX};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, VecDeque};
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant as StdInstant};
+
+// The same as kernel/src/config.rs.
+const NUM_TASKS: u32 = 64;
+const NUM_TIMERS_PER_TASK: u32 = 8;
+
+const KERNEL_TID: u32 = 0;
+const INIT_TID: u32 = 1;
+const IPC_ANY: u32 = 0;
+const IPC_DENY: u32 = u32::MAX;
+
+// Unwinds the thread of an exited or destroyed task.
+pub(crate) struct TaskExit;
+
+struct Timer {
+    deadline: StdInstant,
+    // Zero for one-shot timers.
+    period: Duration,
+}
+
+struct SimTask {
+    // Distinguishes a task from the destroyed ones with the same TID whose
+    // threads may still be running.
+    instance: u64,
+    state: TaskState,
+    priority: u32,
+    time_slice: i32,
+    src_tid: u32,
+    dst_tid: u32,
+    pager: u32,
+    message: Message,
+    notifications: Notifications,
+    fired_timers: u32,
+    ipc_deadline: Option<StdInstant>,
+    ipc_timed_out: bool,
+    // Set while the task is in `call`: only such a task accepts `reply`.
+    in_call: bool,
+    // Set when a receiver has taken the message of the task queued in
+    // sending, which is kept in `message` meanwhile.
+    message_taken: bool,
+    // The buffer to receive an out-of-line payload into when receiving, or the
+    // payload to send when queued in sending.
+    ool_buffer: (usize, usize),
+    ool_too_large: bool,
+    senders: VecDeque<u32>,
+    timers: [Option<Timer>; NUM_TIMERS_PER_TASK as usize],
+}
+
+impl SimTask {
+    fn new(instance: u64, pager: u32) -> SimTask {
+        SimTask {
+            instance,
+            state: TaskState::Runnable,
+            priority: TASK_PRIORITY_MAX - 1,
+            time_slice: 0,
+            src_tid: 0,
+            dst_tid: NOT_SENDING,
+            pager,
+            message: notification_message(Notifications::none(), 0),
+            notifications: Notifications::none(),
+            fired_timers: 0,
+            ipc_deadline: None,
+            ipc_timed_out: false,
+            in_call: false,
+            message_taken: false,
+            ool_buffer: (0, 0),
+            ool_too_large: false,
+            senders: VecDeque::new(),
+            timers: Default::default(),
+        }
+    }
+}
+
+// Zero `timeout` means none.
+fn ipc_deadline(timeout: u32) -> Option<StdInstant> {
+    (timeout != 0).then(|| StdInstant::now() + Duration::from_millis(timeout as u64))
+}
+
+fn notification_message(notifications: Notifications, fired_timers: u32) -> Message {
+    let mut message = Message {
+        message_type: MessageType::NOTIFICATIONS,
+        src_tid: KERNEL_TID,
+        ..Message::zeroed()
+    };
+    message.set_payload(&NotificationPayload {
+        notifications,
+        fired_timers,
+    });
+    message
+}
+
+// The kernel state. Unlike the kernel, the current task is passed explicitly.
+struct Kernel {
+    tasks: BTreeMap<u32, SimTask>,
+    next_instance: u64,
+}
+
+// The task running in a thread.
+#[derive(Clone, Copy)]
+pub(crate) struct Current {
+    pub tid: u32,
+    instance: u64,
+}
+
+impl Kernel {
+    fn is_alive(&self, current: Current) -> bool {
+        self.tasks.get(&current.tid).is_some_and(|task| {
+            task.state != TaskState::Unused && task.instance == current.instance
+        })
+    }
+
+    fn add_task(&mut self, tid: u32, pager: u32) -> Current {
+        self.next_instance += 1;
+        self.tasks
+            .insert(tid, SimTask::new(self.next_instance, pager));
+        Current {
+            tid,
+            instance: self.next_instance,
+        }
+    }
+
+    fn lookup(&self, tid: u32) -> KResult<()> {
+        if tid == KERNEL_TID || tid >= NUM_TASKS {
+            return KResult::InvalidArg;
+        }
+        match self.tasks.get(&tid) {
+            Some(task) if task.state != TaskState::Unused => KResult::Ok(()),
+            _ => KResult::InvalidTask,
+        }
+    }
+
+    fn task(&mut self, tid: u32) -> &mut SimTask {
+        self.tasks.get_mut(&tid).unwrap()
+    }
+
+    fn state(&self, tid: u32) -> TaskState {
+        self.tasks
+            .get(&tid)
+            .map_or(TaskState::Unused, |task| task.state)
+    }
+
+    fn resume(&mut self, tid: u32) {
+        let task = self.task(tid);
+        task.ipc_deadline = None;
+        task.state = TaskState::Runnable;
+    }
+
+    fn block(&mut self, tid: u32, timeout: u32) {
+        self.block_until(tid, ipc_deadline(timeout));
+    }
+
+    fn block_until(&mut self, tid: u32, deadline: Option<StdInstant>) {
+        let task = self.task(tid);
+        task.state = TaskState::Blocked;
+        task.ipc_deadline = deadline;
+    }
+
+    fn is_receiver_ready(&self, dst: u32, src_tid: u32) -> bool {
+        let receiver = &self.tasks[&dst];
+        receiver.state == TaskState::Blocked
+            && (receiver.src_tid == IPC_ANY || receiver.src_tid == src_tid)
+    }
+
+    fn abort_ipc(&mut self, tid: u32) {
+        let task = self.task(tid);
+        task.notifications = task.notifications | Notifications::aborted();
+        self.resume(tid);
+    }
+
+    // Consumes the result of a blocking IPC operation.
+    fn ipc_result(&mut self, tid: u32) -> KResult<()> {
+        let task = self.task(tid);
+        if task.notifications.is_aborted() {
+            task.notifications = task.notifications.clear(Notifications::aborted());
+            return KResult::Aborted;
+        }
+        if task.ipc_timed_out {
+            task.ipc_timed_out = false;
+            return KResult::TryAgain;
+        }
+        if task.ool_too_large {
+            task.ool_too_large = false;
+            return KResult::TooLarge;
+        }
+        KResult::Ok(())
+    }
+
+    // Fails the queued senders whose out-of-line payload doesn't fit in the
+    // buffer of `receiver`, keeping its capacity in their `message.ool_len`.
+    fn reject_too_large_senders(&mut self, receiver: u32, src_tid: u32) {
+        let capacity = self.tasks[&receiver].ool_buffer.1;
+        while let Some(sender) = self
+            .find_sender(receiver, src_tid)
+            .filter(|sender| self.tasks[sender].ool_buffer.1 > capacity)
+        {
+            self.remove_sender(receiver, sender);
+            let task = self.task(sender);
+            task.message.ool_len = capacity;
+            task.ool_too_large = true;
+            self.resume(sender);
+        }
+    }
+
+    // Fails `sender` sending `message` to `dst` waiting for it if the
+    // out-of-line payload doesn't fit, as `reject_too_large_senders` does.
+    fn check_ool_fits(&mut self, sender: u32, dst: u32, message: &Message) -> KResult<()> {
+        let capacity = self.tasks[&dst].ool_buffer.1;
+        if message.ool_len > capacity {
+            self.task(sender).message.ool_len = capacity;
+            return KResult::TooLarge;
+        }
+        KResult::Ok(())
+    }
+
+    fn deliver(&mut self, dst: u32, message: &Message, src_tid: u32) -> KResult<()> {
+        let receiver = self.task(dst);
+        let (buffer, capacity) = receiver.ool_buffer;
+        if message.ool_len > capacity {
+            return KResult::TooLarge;
+        }
+        if message.ool_len > 0 {
+            // The receiver is blocked: nothing else accesses its buffer.
+            unsafe {
+                std::ptr::copy_nonoverlapping(
+                    message.ool_ptr as *const u8,
+                    buffer as *mut u8,
+                    message.ool_len,
+                );
+            }
+        }
+        receiver.message = *message;
+        receiver.message.src_tid = src_tid;
+        receiver.message.ool_ptr = buffer;
+        KResult::Ok(())
+    }
+
+    // Delivers the message of `sender` queued for `receiver` without blocking
+    // the receiver. A caller in `call` goes on to wait for the reply.
+    fn take_queued_message(&mut self, receiver: u32, sender: u32) -> KResult<()> {
+        let queued = self.tasks[&sender].message;
+        self.deliver(receiver, &queued, queued.src_tid)?;
+        self.remove_sender(receiver, sender);
+        let task = self.task(sender);
+        task.message_taken = true;
+        if task.in_call {
+            task.ool_buffer = (0, 0);
+            task.src_tid = receiver;
+        } else {
+            self.resume(sender);
+        }
+        KResult::Ok(())
+    }
+
+    fn find_sender(&self, receiver: u32, src_tid: u32) -> Option<u32> {
+        self.tasks[&receiver]
+            .senders
+            .iter()
+            .copied()
+            .find(|sender| src_tid == IPC_ANY || src_tid == *sender)
+    }
+
+    fn remove_sender(&mut self, receiver: u32, sender: u32) {
+        if let Some(receiver) = self.tasks.get_mut(&receiver) {
+            receiver.senders.retain(|tid| *tid != sender);
+        }
+        self.task(sender).dst_tid = NOT_SENDING;
+    }
+
+    fn notify(&mut self, dst: u32, notifications: Notifications) {
+        let task = self.task(dst);
+        if task.state == TaskState::Blocked && task.src_tid == IPC_ANY {
+            let fired_timers = task.fired_timers;
+            task.message = notification_message(task.notifications | notifications, fired_timers);
+            task.fired_timers = 0;
+            task.notifications = Notifications::none();
+            self.resume(dst);
+        } else {
+            task.notifications = task.notifications | notifications;
+        }
+    }
+
+    fn time_out(&mut self, tid: u32) {
+        let dst_tid = self.task(tid).dst_tid;
+        if dst_tid != NOT_SENDING {
+            self.remove_sender(dst_tid, tid);
+        }
+        self.task(tid).ipc_timed_out = true;
+        self.resume(tid);
+    }
+
+    // Fires the expired timers and times out the expired IPC operations.
+    // Returns true if any task has been notified or resumed.
+    fn expire(&mut self, now: StdInstant) -> bool {
+        let mut changed = false;
+        let tids: Vec<u32> = self.tasks.keys().copied().collect();
+        for tid in tids {
+            if self.state(tid) == TaskState::Unused {
+                continue;
+            }
+            for id in 0..NUM_TIMERS_PER_TASK {
+                let task = self.task(tid);
+                let fired = match &mut task.timers[id as usize] {
+                    Some(timer) if timer.deadline <= now => {
+                        if timer.period.is_zero() {
+                            task.timers[id as usize] = None;
+                        } else {
+                            // Skip the missed periods.
+                            while timer.deadline <= now {
+                                timer.deadline += timer.period;
+                            }
+                        }
+                        true
+                    }
+                    _ => false,
+                };
+                if fired {
+                    task.fired_timers |= 1 << id;
+                    self.notify(tid, Notifications::timer());
+                    changed = true;
+                }
+            }
+            let task = self.task(tid);
+            if task.state == TaskState::Blocked && task.ipc_deadline.is_some_and(|d| d <= now) {
+                self.time_out(tid);
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    fn next_deadline(&self) -> Option<StdInstant> {
+        self.tasks
+            .values()
+            .filter(|task| task.state != TaskState::Unused)
+            .flat_map(|task| {
+                task.timers
+                    .iter()
+                    .flatten()
+                    .map(|timer| timer.deadline)
+                    .chain(task.ipc_deadline)
+            })
+            .min()
+    }
+
+    fn release(&mut self, tid: u32) {
+        let task = self.task(tid);
+        let dst_tid = task.dst_tid;
+        task.state = TaskState::Unused;
+        task.timers = Default::default();
+        if dst_tid != NOT_SENDING {
+            self.remove_sender(dst_tid, tid);
+        }
+
+        // Abort tasks blocked on sending to the task.
+        while let Some(sender) = self.task(tid).senders.pop_front() {
+            self.task(sender).dst_tid = NOT_SENDING;
+            self.abort_ipc(sender);
+        }
+
+        // Abort tasks waiting for a message from the task (e.g. a reply of ipc_call).
+        let waiters: Vec<u32> = self
+            .tasks
+            .iter()
+            .filter(|(_, waiter)| waiter.state == TaskState::Blocked && waiter.src_tid == tid)
+            .map(|(tid, _)| *tid)
+            .collect();
+        for waiter in waiters {
+            self.abort_ipc(waiter);
+        }
+    }
+}
+
+/// A simulated system. Tests create their own one so that they can run in
+/// parallel.
+///
+/// Tasks are threads scheduled by the host, so priorities are only recorded:
+/// there is no priority inheritance, and `task_info` reports the priority set
+/// by `schedule_task` as both `priority` and `base_priority`. The quantum and
+/// the stack fields (`kernel_stack_peak`, `stack_size` and `stack_peak`) are
+/// always zero.
+pub struct System {
+    kernel: Mutex<Kernel>,
+    // Notified on every change of the task states.
+    changed: Condvar,
+    boot: StdInstant,
+    // Entry points for `create_task`. `pc` is an index into this.
+    entries: Mutex<Vec<fn()>>,
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<(Arc<System>, Current)>> = const { RefCell::new(None) };
+}
+
+// Calls `f` with the system and the task running in this thread.
+pub(crate) fn with_current<R>(f: impl FnOnce(&Arc<System>, Current) -> R) -> R {
+    let current = CURRENT.with(|current| current.borrow().clone());
+    match current {
+        Some((system, current)) => f(&system, current),
+        None => panic!("syscall from a thread which is not a task: use System::spawn_task"),
+    }
+}
+
+impl System {
+    pub fn new() -> Arc<System> {
+        Arc::new(System {
+            kernel: Mutex::new(Kernel {
+                tasks: BTreeMap::new(),
+                next_instance: 0,
+            }),
+            changed: Condvar::new(),
+            boot: StdInstant::now(),
+            entries: Mutex::new(Vec::new()),
+        })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Kernel> {
+        let mut kernel = self.kernel.lock().unwrap();
+        if kernel.expire(StdInstant::now()) {
+            self.changed.notify_all();
+        }
+        kernel
+    }
+
+    // Enters the kernel from `current`. Unwinds the thread if the task has
+    // been destroyed.
+    fn enter(&self, current: Current) -> MutexGuard<'_, Kernel> {
+        let kernel = self.lock();
+        if !kernel.is_alive(current) {
+            drop(kernel);
+            panic::resume_unwind(Box::new(TaskExit));
+        }
+        kernel
+    }
+
+    // Waits until `current` is resumed. Unwinds the thread if the task has
+    // been destroyed meanwhile.
+    fn wait<'a>(
+        &self,
+        mut kernel: MutexGuard<'a, Kernel>,
+        current: Current,
+    ) -> MutexGuard<'a, Kernel> {
+        self.changed.notify_all();
+        while kernel.is_alive(current) && kernel.state(current.tid) == TaskState::Blocked {
+            kernel = match kernel.next_deadline() {
+                Some(deadline) => {
+                    let timeout = deadline.saturating_duration_since(StdInstant::now());
+                    self.changed.wait_timeout(kernel, timeout).unwrap().0
+                }
+                None => self.changed.wait(kernel).unwrap(),
+            };
+            if kernel.expire(StdInstant::now()) {
+                self.changed.notify_all();
+            }
+        }
+        if !kernel.is_alive(current) {
+            drop(kernel);
+            panic::resume_unwind(Box::new(TaskExit));
+        }
+        kernel
+    }
+
+    // Registers the entry point of a task and returns the `pc` to pass to
+    // `create_task`.
+    pub fn register_entry(&self, entry: fn()) -> u32 {
+        let mut entries = self.entries.lock().unwrap();
+        entries.push(entry);
+        entries.len() as u32
+    }
+
+    // Creates a task `tid` running `f` in a new thread. The task exits when `f`
+    // returns.
+    pub fn spawn_task<F: FnOnce() + Send + 'static>(
+        self: &Arc<Self>,
+        tid: u32,
+        f: F,
+    ) -> KResult<()> {
+        self.spawn_task_with_pager(tid, KERNEL_TID, f)
+    }
+
+    fn spawn_task_with_pager<F: FnOnce() + Send + 'static>(
+        self: &Arc<Self>,
+        tid: u32,
+        pager: u32,
+        f: F,
+    ) -> KResult<()> {
+        if tid == KERNEL_TID || tid >= NUM_TASKS {
+            return KResult::InvalidArg;
+        }
+        let mut kernel = self.lock();
+        if kernel.state(tid) != TaskState::Unused {
+            return KResult::AlreadyExists;
+        }
+        let task = kernel.add_task(tid, pager);
+        drop(kernel);
+
+        let system = self.clone();
+        thread::spawn(move || {
+            CURRENT.with(|current| *current.borrow_mut() = Some((system.clone(), task)));
+            let result = panic::catch_unwind(AssertUnwindSafe(f));
+            system.exit(task);
+            if let Err(payload) = result {
+                if !payload.is::<TaskExit>() {
+                    panic::resume_unwind(payload);
+                }
+            }
+        });
+        KResult::Ok(())
+    }
+
+    // Runs `f` in the current thread as the task `tid`, e.g. a client in a test.
+    // The task exits when `f` returns, so `f` must not call `exit_task`.
+    pub fn run_as_task<R>(self: &Arc<Self>, tid: u32, f: impl FnOnce() -> R) -> R {
+        let task = {
+            let mut kernel = self.lock();
+            assert!(
+                kernel.state(tid) == TaskState::Unused,
+                "task {} already exists",
+                tid
+            );
+            kernel.add_task(tid, KERNEL_TID)
+        };
+        CURRENT.with(|current| *current.borrow_mut() = Some((self.clone(), task)));
+        let result = panic::catch_unwind(AssertUnwindSafe(f));
+        CURRENT.with(|current| *current.borrow_mut() = None);
+        self.exit(task);
+        match result {
+            Ok(r) => r,
+            Err(payload) => panic::resume_unwind(payload),
+        }
+    }
+
+    pub(crate) fn create_task(
+        self: &Arc<Self>,
+        current: Current,
+        tid: u32,
+        pc: u32,
+    ) -> KResult<()> {
+        drop(self.enter(current));
+        let entry = pc
+            .checked_sub(1)
+            .and_then(|i| self.entries.lock().unwrap().get(i as usize).copied());
+        match entry {
+            Some(entry) => self.spawn_task_with_pager(tid, current.tid, entry),
+            None => KResult::InvalidArg,
+        }
+    }
+
+    pub(crate) fn destroy_task(&self, current: Current, tid: u32) -> KResult<()> {
+        let mut kernel = self.enter(current);
+        kernel.lookup(tid)?;
+        if current.tid != INIT_TID && current.tid != kernel.task(tid).pager {
+            return KResult::NotPermitted;
+        }
+        if tid == current.tid {
+            return KResult::InvalidArg;
+        }
+        kernel.release(tid);
+        self.changed.notify_all();
+        KResult::Ok(())
+    }
+
+    pub(crate) fn exit(&self, current: Current) {
+        let mut kernel = self.lock();
+        if kernel.is_alive(current) {
+            kernel.release(current.tid);
+        }
+        self.changed.notify_all();
+    }
+
+    pub(crate) fn send(
+        &self,
+        current: Current,
+        dst: u32,
+        message: &mut Message,
+        flags: IpcFlags,
+        timeout: u32,
+    ) -> KResult<()> {
+        let result = self
+            .send_locked(self.enter(current), current, dst, message, flags, timeout)
+            .map(|_| ());
+        self.report_ool_capacity(current, message, result)
+    }
+
+    // On `KResult::TooLarge`, tells the sender the capacity of the buffer of
+    // the receiver in `message.ool_len` as the kernel does.
+    fn report_ool_capacity<T>(
+        &self,
+        current: Current,
+        message: &mut Message,
+        result: KResult<T>,
+    ) -> KResult<T> {
+        if let KResult::TooLarge = result {
+            message.ool_len = self.lock().tasks[&current.tid].message.ool_len;
+        }
+        result
+    }
+
+    // Returns the lock back so that `call` enters the receive phase before
+    // the receiver can reply, and whether the receiver has taken the message
+    // from the queue, in which case a caller has already got the reply.
+    fn send_locked<'a>(
+        &'a self,
+        mut kernel: MutexGuard<'a, Kernel>,
+        current: Current,
+        dst: u32,
+        message: &Message,
+        flags: IpcFlags,
+        timeout: u32,
+    ) -> KResult<(MutexGuard<'a, Kernel>, bool)> {
+        kernel.lookup(dst)?;
+        if !kernel.is_receiver_ready(dst, current.tid) {
+            if flags.is_noblock() {
+                return KResult::WouldBlock;
+            }
+
+            let task = kernel.task(current.tid);
+            task.message = *message;
+            task.message.src_tid = current.tid;
+            task.ool_buffer = (message.ool_ptr, message.ool_len);
+            let deadline = ipc_deadline(timeout);
+            loop {
+                let task = kernel.task(current.tid);
+                task.src_tid = IPC_DENY;
+                task.dst_tid = dst;
+                kernel.task(dst).senders.push_back(current.tid);
+                kernel.block_until(current.tid, deadline);
+                kernel = self.wait(kernel, current);
+                let taken = mem::take(&mut kernel.task(current.tid).message_taken);
+                kernel.ipc_result(current.tid)?;
+                if taken {
+                    return KResult::Ok((kernel, true));
+                }
+                if kernel.state(dst) == TaskState::Unused {
+                    // The receiver has been destroyed after it resumed us.
+                    return KResult::Aborted;
+                }
+                if kernel.is_receiver_ready(dst, current.tid) {
+                    break;
+                }
+                // The receiver has stopped waiting before we ran, e.g. its
+                // receive has timed out: wait for it again.
+            }
+        }
+        kernel.check_ool_fits(current.tid, dst, message)?;
+        kernel.deliver(dst, message, current.tid)?;
+        kernel.resume(dst);
+        self.changed.notify_all();
+        KResult::Ok((kernel, false))
+    }
+
+    pub(crate) fn reply(&self, current: Current, dst: u32, message: &Message) -> KResult<()> {
+        if message.ool_len > 0 {
+            return KResult::NotAcceptable;
+        }
+        let kernel = self.enter(current);
+        kernel.lookup(dst)?;
+        let receiver = &kernel.tasks[&dst];
+        if !receiver.in_call
+            || receiver.state != TaskState::Blocked
+            || receiver.src_tid != current.tid
+        {
+            return KResult::NotReady;
+        }
+        self.send_locked(kernel, current, dst, message, IpcFlags::noblock(), 0)
+            .map(|_| ())
+    }
+
+    pub(crate) fn recv(
+        &self,
+        current: Current,
+        src_tid: u32,
+        flags: IpcFlags,
+        timeout: u32,
+    ) -> KResult<Message> {
+        self.recv_locked(
+            self.enter(current),
+            current,
+            src_tid,
+            (0, 0),
+            flags,
+            timeout,
+        )
+    }
+
+    pub(crate) fn recv_ool(
+        &self,
+        current: Current,
+        src_tid: u32,
+        buffer: &mut [u8],
+    ) -> KResult<Message> {
+        let buffer = (buffer.as_mut_ptr() as usize, buffer.len());
+        self.recv_locked(
+            self.enter(current),
+            current,
+            src_tid,
+            buffer,
+            IpcFlags::block(),
+            0,
+        )
+    }
+
+    fn recv_locked(
+        &self,
+        mut kernel: MutexGuard<'_, Kernel>,
+        current: Current,
+        src_tid: u32,
+        ool_buffer: (usize, usize),
+        flags: IpcFlags,
+        timeout: u32,
+    ) -> KResult<Message> {
+        if src_tid >= NUM_TASKS {
+            return KResult::InvalidArg;
+        }
+        let task = kernel.task(current.tid);
+        if src_tid == IPC_ANY && task.notifications.exists() {
+            let message = notification_message(task.notifications, task.fired_timers);
+            task.fired_timers = 0;
+            task.notifications = Notifications::none();
+            return KResult::Ok(message);
+        }
+
+        kernel.task(current.tid).ool_buffer = ool_buffer;
+        kernel.reject_too_large_senders(current.tid, src_tid);
+        let sender = kernel.find_sender(current.tid, src_tid);
+        if flags.is_noblock() {
+            match sender {
+                Some(sender) => kernel.take_queued_message(current.tid, sender)?,
+                None => return KResult::WouldBlock,
+            }
+            self.changed.notify_all();
+            return KResult::Ok(kernel.task(current.tid).message);
+        }
+        match sender {
+            Some(sender) => {
+                kernel.resume(sender);
+                kernel.remove_sender(current.tid, sender);
+                kernel.task(current.tid).src_tid = sender;
+            }
+            None => kernel.task(current.tid).src_tid = src_tid,
+        }
+        kernel.block(current.tid, timeout);
+        kernel = self.wait(kernel, current);
+        kernel.ipc_result(current.tid)?;
+        KResult::Ok(kernel.task(current.tid).message)
+    }
+
+    pub(crate) fn call(
+        &self,
+        current: Current,
+        dst: u32,
+        message: &mut Message,
+        timeout: u32,
+    ) -> KResult<Message> {
+        let mut kernel = self.enter(current);
+        kernel.task(current.tid).in_call = true;
+        let result = self
+            .send_locked(kernel, current, dst, message, IpcFlags::block(), timeout)
+            .and_then(|(mut kernel, taken)| {
+                if taken {
+                    return KResult::Ok(kernel.task(current.tid).message);
+                }
+                self.recv_locked(kernel, current, dst, (0, 0), IpcFlags::block(), timeout)
+            });
+        self.enter(current).task(current.tid).in_call = false;
+        self.report_ool_capacity(current, message, result)
+    }
+
+    // Sends `notifications` to `dst` as the kernel does, e.g. to simulate an
+    // interrupt.
+    pub fn notify(&self, dst: u32, notifications: Notifications) -> KResult<()> {
+        let mut kernel = self.lock();
+        kernel.lookup(dst)?;
+        kernel.notify(dst, notifications);
+        self.changed.notify_all();
+        KResult::Ok(())
+    }
+
+    pub(crate) fn ipc_abort(&self, current: Current, tid: u32) -> KResult<()> {
+        let mut kernel = self.enter(current);
+        kernel.lookup(tid)?;
+        if current.tid != INIT_TID && current.tid != kernel.task(tid).pager {
+            return KResult::NotPermitted;
+        }
+        if tid == current.tid {
+            return KResult::NotPermitted;
+        }
+        if kernel.state(tid) != TaskState::Blocked {
+            return KResult::NotReady;
+        }
+        let dst_tid = kernel.task(tid).dst_tid;
+        if dst_tid != NOT_SENDING {
+            kernel.remove_sender(dst_tid, tid);
+        }
+        kernel.abort_ipc(tid);
+        self.changed.notify_all();
+        KResult::Ok(())
+    }
+
+    pub(crate) fn timer_set(
+        &self,
+        current: Current,
+        id: u32,
+        deadline: StdInstant,
+        period: u32,
+    ) -> KResult<()> {
+        if id >= NUM_TIMERS_PER_TASK {
+            return KResult::InvalidArg;
+        }
+        let mut kernel = self.enter(current);
+        let task = kernel.task(current.tid);
+        task.fired_timers &= !(1 << id);
+        task.timers[id as usize] = Some(Timer {
+            deadline,
+            period: Duration::from_millis(period as u64),
+        });
+        self.changed.notify_all();
+        KResult::Ok(())
+    }
+
+    pub(crate) fn timer_cancel(&self, current: Current, id: u32) -> KResult<()> {
+        if id >= NUM_TIMERS_PER_TASK {
+            return KResult::InvalidArg;
+        }
+        let mut kernel = self.enter(current);
+        let task = kernel.task(current.tid);
+        task.fired_timers &= !(1 << id);
+        task.timers[id as usize] = None;
+        KResult::Ok(())
+    }
+
+    // The time when the system is created, i.e. `Instant::from_micros(0)`.
+    pub(crate) fn boot_time(&self) -> StdInstant {
+        self.boot
+    }
+
+    pub(crate) fn schedule_task(
+        &self,
+        current: Current,
+        tid: u32,
+        priority: u32,
+        time_slice: u32,
+    ) -> KResult<()> {
+        if priority >= TASK_PRIORITY_MAX || (time_slice as i32) < 0 {
+            return KResult::InvalidArg;
+        }
+        let mut kernel = self.enter(current);
+        kernel.lookup(tid)?;
+        let task = kernel.task(tid);
+        task.priority = priority;
+        task.time_slice = time_slice as i32;
+        KResult::Ok(())
+    }
+
+    pub(crate) fn task_info(&self, current: Current, tid: u32) -> KResult<TaskInfo> {
+        let mut kernel = self.enter(current);
+        kernel.lookup(tid)?;
+        let now = StdInstant::now();
+        let task = kernel.task(tid);
+        let timeout = task.timers[0].as_ref().map_or(0, |timer| {
+            timer.deadline.saturating_duration_since(now).as_millis() as u32
+        });
+        KResult::Ok(TaskInfo {
+            tid,
+            state: task.state,
+            task_type: TaskType::User,
+            priority: task.priority,
+            time_slice: task.time_slice,
+            quantum: 0,
+            notifications: task.notifications,
+            src_tid: task.src_tid,
+            dst_tid: task.dst_tid,
+            timeout,
+            pager: task.pager,
+            kernel_stack_peak: 0,
+            stack_size: 0,
+            stack_peak: 0,
+            // No priority inheritance (see `System`).
+            base_priority: task.priority,
+        })
+    }
+}
@@ -0,0 +1,145 @@
+use super::sim::{self, TaskExit};
+use ::klib::ipc::{IpcFlags, Message};
+use ::klib::result::KResult;
+use ::klib::task::TaskInfo;
+use ::klib::time::{Duration, Instant};
+use std::io::{self, Write};
+use std::panic;
+use std::time::Instant as StdInstant;
+
+pub fn nop() -> KResult<()> {
+    KResult::Ok(())
+}
+
+pub fn set_timer(timeout: u32) -> KResult<()> {
+    if timeout == 0 {
+        return timer_cancel(0);
+    }
+    timer_set(0, timeout, 0)
+}
+
+pub fn timer_set(id: u32, ms: u32, period: u32) -> KResult<()> {
+    if ms == 0 {
+        return KResult::InvalidArg;
+    }
+    let deadline = StdInstant::now() + Duration::from_millis(ms as u64);
+    sim::with_current(|system, current| system.timer_set(current, id, deadline, period))
+}
+
+pub fn timer_cancel(id: u32) -> KResult<()> {
+    sim::with_current(|system, current| system.timer_cancel(current, id))
+}
+
+pub fn timer_set_until(id: u32, deadline: Instant) -> KResult<()> {
+    sim::with_current(|system, current| {
+        let deadline = system.boot_time() + Duration::from_micros(deadline.as_micros());
+        system.timer_set(current, id, deadline, 0)
+    })
+}
+
+pub fn get_time() -> KResult<Instant> {
+    sim::with_current(|system, _| {
+        KResult::Ok(Instant::from_micros(
+            system.boot_time().elapsed().as_micros() as u64,
+        ))
+    })
+}
+
+pub fn console_write(s: &[u8]) -> KResult<()> {
+    let _ = io::stdout().write_all(s);
+    KResult::Ok(())
+}
+
+pub fn ipc_recv(src_tid: u32) -> KResult<Message> {
+    sim::with_current(|system, current| system.recv(current, src_tid, IpcFlags::block(), 0))
+}
+
+pub fn ipc_recv_noblock(src_tid: u32) -> KResult<Message> {
+    sim::with_current(|system, current| system.recv(current, src_tid, IpcFlags::noblock(), 0))
+}
+
+pub fn ipc_recv_timeout(src_tid: u32, timeout: u32) -> KResult<Message> {
+    sim::with_current(|system, current| system.recv(current, src_tid, IpcFlags::block(), timeout))
+}
+
+pub fn ipc_recv_ool(src_tid: u32, buffer: &mut [u8]) -> KResult<Message> {
+    sim::with_current(|system, current| system.recv_ool(current, src_tid, buffer))
+}
+
+pub fn ipc_send(dst_tid: u32, message: &mut Message) -> KResult<()> {
+    sim::with_current(|system, current| {
+        system.send(current, dst_tid, message, IpcFlags::block(), 0)
+    })
+}
+
+pub fn ipc_call(dst_tid: u32, message: &mut Message) -> KResult<Message> {
+    sim::with_current(|system, current| system.call(current, dst_tid, message, 0))
+}
+
+pub fn ipc_send_noblock(dst_tid: u32, message: &mut Message) -> KResult<()> {
+    sim::with_current(|system, current| {
+        system.send(current, dst_tid, message, IpcFlags::noblock(), 0)
+    })
+}
+
+pub fn ipc_send_timeout(dst_tid: u32, message: &mut Message, timeout: u32) -> KResult<()> {
+    sim::with_current(|system, current| {
+        system.send(current, dst_tid, message, IpcFlags::block(), timeout)
+    })
+}
+
+pub fn ipc_call_timeout(dst_tid: u32, message: &mut Message, timeout: u32) -> KResult<Message> {
+    sim::with_current(|system, current| system.call(current, dst_tid, message, timeout))
+}
+
+pub fn ipc_reply(dst_tid: u32, message: &Message) -> KResult<()> {
+    sim::with_current(|system, current| system.reply(current, dst_tid, message))
+}
+
+pub fn ipc_abort(tid: u32) -> KResult<()> {
+    sim::with_current(|system, current| system.ipc_abort(current, tid))
+}
+
+// `pc` is a value returned by `System::register_entry`. Each task runs on its
+// own thread, so `sp` is ignored.
+pub fn create_task(tid: u32, pc: u32, _sp: u32) -> KResult<()> {
+    sim::with_current(|system, current| system.create_task(current, tid, pc))
+}
+
+pub fn create_task_with_stack(tid: u32, pc: u32, _stack: &'static mut [u8]) -> KResult<()> {
+    sim::with_current(|system, current| system.create_task(current, tid, pc))
+}
+
+pub fn destroy_task(tid: u32) -> KResult<()> {
+    sim::with_current(|system, current| system.destroy_task(current, tid))
+}
+
+pub fn exit_task() -> ! {
+    sim::with_current(|system, current| system.exit(current));
+    panic::resume_unwind(Box::new(TaskExit));
+}
+
+pub fn task_self() -> KResult<u32> {
+    sim::with_current(|_, current| KResult::Ok(current.tid))
+}
+
+pub fn task_info(tid: u32) -> KResult<TaskInfo> {
+    sim::with_current(|system, current| system.task_info(current, tid))
+}
+
+pub fn schedule_task(tid: u32, priority: u32, time_slice: u32) -> KResult<()> {
+    sim::with_current(|system, current| system.schedule_task(current, tid, priority, time_slice))
+}
+
+// There are no devices. Tests deliver interrupts with `System::notify`.
+pub fn irq_acquire(_irq: u32) -> KResult<()> {
+    KResult::NotPermitted
+}
+
+pub fn irq_release(_irq: u32) -> KResult<()> {
+    KResult::NotPermitted
+}
+
+pub fn irq_ack(_irq: u32) -> KResult<()> {
+    KResult::NotPermitted
+}
@@ -1,5 +1,7 @@
 use ::klib::ipc::Message;
 use ::klib::result::KResult;
+use ::klib::task::TaskInfo;
+use ::klib::time::Instant;
 
 pub fn nop() -> KResult<()> {
     unimplemented!();
@@ -9,6 +11,22 @@ pub fn set_timer(_timeout: u32) -> KResult<()> {
     unimplemented!();
 }
 
+pub fn timer_set(_id: u32, _ms: u32, _period: u32) -> KResult<()> {
+    unimplemented!();
+}
+
+pub fn timer_cancel(_id: u32) -> KResult<()> {
+    unimplemented!();
+}
+
+pub fn timer_set_until(_id: u32, _deadline: Instant) -> KResult<()> {
+    unimplemented!();
+}
+
+pub fn get_time() -> KResult<Instant> {
+    unimplemented!();
+}
+
 pub fn console_write(_s: &[u8]) -> KResult<()> {
     unimplemented!();
 }
@@ -17,6 +35,18 @@ pub fn ipc_recv(_src_tid: u32) -> KResult<Message> {
     unimplemented!();
 }
 
+pub fn ipc_recv_noblock(_src_tid: u32) -> KResult<Message> {
+    unimplemented!();
+}
+
+pub fn ipc_recv_timeout(_src_tid: u32, _timeout: u32) -> KResult<Message> {
+    unimplemented!();
+}
+
+pub fn ipc_recv_ool(_src_tid: u32, _buffer: &mut [u8]) -> KResult<Message> {
+    unimplemented!();
+}
+
 pub fn ipc_send(_dst_tid: u32, _message: &Message) -> KResult<()> {
     unimplemented!();
 }
@@ -29,6 +59,58 @@ pub fn ipc_send_noblock(_dst_tid: u32, _message: &Message) -> KResult<()> {
     unimplemented!();
 }
 
+pub fn ipc_send_timeout(_dst_tid: u32, _message: &Message, _timeout: u32) -> KResult<()> {
+    unimplemented!();
+}
+
+pub fn ipc_call_timeout(_dst_tid: u32, _message: &Message, _timeout: u32) -> KResult<Message> {
+    unimplemented!();
+}
+
+pub fn ipc_reply(_dst_tid: u32, _message: &Message) -> KResult<()> {
+    unimplemented!();
+}
+
+pub fn ipc_abort(_tid: u32) -> KResult<()> {
+    unimplemented!();
+}
+
 pub fn create_task(_tid: u32, _pc: u32, _sp: u32) -> KResult<()> {
     unimplemented!();
 }
+
+pub fn create_task_with_stack(_tid: u32, _pc: u32, _stack: &'static mut [u8]) -> KResult<()> {
+    unimplemented!();
+}
+
+pub fn destroy_task(_tid: u32) -> KResult<()> {
+    unimplemented!();
+}
+
+pub fn exit_task() -> ! {
+    unimplemented!();
+}
+
+pub fn task_self() -> KResult<u32> {
+    unimplemented!();
+}
+
+pub fn task_info(_tid: u32) -> KResult<TaskInfo> {
+    unimplemented!();
+}
+
+pub fn schedule_task(_tid: u32, _priority: u32, _time_slice: u32) -> KResult<()> {
+    unimplemented!();
+}
+
+pub fn irq_acquire(_irq: u32) -> KResult<()> {
+    unimplemented!();
+}
+
+pub fn irq_release(_irq: u32) -> KResult<()> {
+    unimplemented!();
+}
+
+pub fn irq_ack(_irq: u32) -> KResult<()> {
+    unimplemented!();
+}
@@ -3,9 +3,13 @@ use core::mem;
 use klib::buf_writer::BufWriter;
 
 pub fn print_error_impl<const N: usize>(format: &[u8], err: u32) {
+    print_info_impl::<N>(format, err)
+}
+
+pub fn print_info_impl<const N: usize>(format: &[u8], value: u32) {
     let mut buf = [mem::MaybeUninit::uninit(); N];
     let mut writer = BufWriter::new(&mut buf);
-    klib::buf_fmt!(&mut writer, format, err);
+    klib::buf_fmt!(&mut writer, format, value);
     syscall::console_write(writer.as_slice());
 }
 
@@ -15,3 +19,12 @@ macro_rules! print_error {
         $crate::error::print_error_impl::<{ $message.len() + 8 }>($message, $err)
     };
 }
+
+// Prints a message which is not an error, with a value formatted as by
+// `print_error!`.
+#[macro_export]
+macro_rules! print_info {
+    ($message:expr, $value:expr) => {
+        $crate::error::print_info_impl::<{ $message.len() + 8 }>($message, $value)
+    };
+}
@@ -0,0 +1,275 @@
+use crate::hosted::System;
+use crate::syscall::*;
+use klib::ipc::{Message, MessageType, NotificationPayload, Notifications};
+use klib::result::KResult;
+use klib::task::{TaskState, NOT_SENDING};
+use klib::time::Duration;
+use std::sync::mpsc;
+
+const ECHO: MessageType = MessageType(100);
+
+fn message(message_type: MessageType, value: u8) -> Message {
+    let mut message = Message {
+        message_type,
+        ..Message::zeroed()
+    };
+    message.raw[0] = value;
+    message
+}
+
+// Replies to each message with its value incremented.
+fn echo_server() {
+    loop {
+        let request = ipc_recv(0).ok().unwrap();
+        let reply = message(ECHO, request.raw[0] + 1);
+        ipc_reply(request.src_tid, &reply).ok().unwrap();
+    }
+}
+
+#[test]
+fn hosted_call_and_reply() {
+    let system = System::new();
+    system.spawn_task(2, echo_server).ok().unwrap();
+    system.run_as_task(1, || {
+        for i in 0..10 {
+            let reply = ipc_call(2, &mut message(ECHO, i)).ok().unwrap();
+            assert_eq!(reply.src_tid, 2);
+            assert_eq!(reply.raw[0], i + 1);
+        }
+        assert_eq!(task_self().ok().unwrap(), 1);
+    });
+}
+
+#[test]
+fn hosted_noblock() {
+    let system = System::new();
+    system
+        .spawn_task(2, || {
+            // Only accepts messages from the task 3.
+            let _ = ipc_recv(3);
+        })
+        .ok()
+        .unwrap();
+    system.run_as_task(1, || {
+        assert!(matches!(ipc_recv_noblock(0), KResult::WouldBlock));
+        assert!(matches!(
+            ipc_send_noblock(2, &mut message(ECHO, 0)),
+            KResult::WouldBlock
+        ));
+        assert!(matches!(
+            ipc_send(9, &mut message(ECHO, 0)),
+            KResult::InvalidTask
+        ));
+    });
+}
+
+#[test]
+fn hosted_reply_never_blocks() {
+    let system = System::new();
+    system
+        .spawn_task(2, || loop {
+            let _ = ipc_recv(1);
+        })
+        .ok()
+        .unwrap();
+    system.run_as_task(1, || {
+        while task_info(2).ok().unwrap().state != TaskState::Blocked {
+            std::thread::yield_now();
+        }
+        // The task 2 waits for a message from us, but not for a reply in
+        // `ipc_call`, and the task 3 doesn't exist.
+        assert!(matches!(ipc_reply(2, &message(ECHO, 0)), KResult::NotReady));
+        assert!(matches!(
+            ipc_reply(3, &message(ECHO, 0)),
+            KResult::InvalidTask
+        ));
+    });
+}
+
+#[test]
+fn hosted_ool_payload() {
+    let system = System::new();
+    let (tx, rx) = mpsc::channel();
+    system
+        .spawn_task(2, move || {
+            let mut buffer = [0; 8];
+            let message = ipc_recv_ool(0, &mut buffer).ok().unwrap();
+            tx.send(buffer[..message.ool_len].to_vec()).unwrap();
+        })
+        .ok()
+        .unwrap();
+    system.run_as_task(1, || {
+        let mut request = message(ECHO, 0);
+        request.set_ool(b"too large");
+        assert!(matches!(ipc_send(2, &mut request), KResult::TooLarge));
+        // Retries with as much of the payload as the receiver accepts.
+        assert_eq!(request.ool_len, 8);
+        request.set_ool(&b"too large"[..request.ool_len]);
+        ipc_send(2, &mut request).ok().unwrap();
+    });
+    assert_eq!(rx.recv().unwrap(), b"too larg");
+}
+
+#[test]
+fn hosted_ool_reply_is_rejected() {
+    let system = System::new();
+    system
+        .spawn_task(2, || {
+            let request = ipc_recv(0).ok().unwrap();
+            let mut reply = message(ECHO, 1);
+            reply.set_ool(b"payload");
+            assert!(matches!(
+                ipc_reply(request.src_tid, &reply),
+                KResult::NotAcceptable
+            ));
+            ipc_reply(request.src_tid, &message(ECHO, 2)).ok().unwrap();
+        })
+        .ok()
+        .unwrap();
+    system.run_as_task(1, || {
+        let reply = ipc_call(2, &mut message(ECHO, 0)).ok().unwrap();
+        assert_eq!(reply.raw[0], 2);
+        assert_eq!(reply.ool_len, 0);
+    });
+}
+
+#[test]
+fn hosted_noblock_recv_takes_queued_messages() {
+    let system = System::new();
+    let (tx, rx) = mpsc::channel();
+    system
+        .spawn_task(2, || ipc_send(1, &mut message(ECHO, 2)).ok().unwrap())
+        .ok()
+        .unwrap();
+    system
+        .spawn_task(3, move || {
+            tx.send(ipc_call(1, &mut message(ECHO, 3)).ok().unwrap().raw[0])
+                .unwrap();
+        })
+        .ok()
+        .unwrap();
+    system.run_as_task(1, || {
+        for _ in 0..2 {
+            let request = loop {
+                match ipc_recv_noblock(0) {
+                    KResult::Ok(request) => break request,
+                    KResult::WouldBlock => std::thread::yield_now(),
+                    err => panic!("ipc_recv_noblock failed: {}", err.err_as_u32()),
+                }
+            };
+            assert_eq!(request.src_tid, request.raw[0] as u32);
+            if request.src_tid == 3 {
+                ipc_reply(3, &message(ECHO, 4)).ok().unwrap();
+            }
+        }
+    });
+    assert_eq!(rx.recv().unwrap(), 4);
+}
+
+#[test]
+fn hosted_timeout() {
+    let system = System::new();
+    system.spawn_task(2, echo_server).ok().unwrap();
+    system.run_as_task(1, || {
+        let start = get_time().ok().unwrap();
+        assert!(matches!(ipc_recv_timeout(0, 20), KResult::TryAgain));
+        assert!(get_time().ok().unwrap() - start >= Duration::from_millis(20));
+        // Zero means no timeout.
+        let reply = ipc_call_timeout(2, &mut message(ECHO, 1), 0).ok().unwrap();
+        assert_eq!(reply.raw[0], 2);
+    });
+}
+
+#[test]
+fn hosted_timers() {
+    let system = System::new();
+    system.run_as_task(1, || {
+        timer_set(1, 10, 0).ok().unwrap();
+        timer_set(3, 20, 0).ok().unwrap();
+        timer_set(5, 1000, 0).ok().unwrap();
+        timer_cancel(5).ok().unwrap();
+
+        let mut fired = 0;
+        while fired != 0b1010 {
+            let payload = NotificationPayload::parse(&ipc_recv(0).ok().unwrap()).unwrap();
+            assert!(payload.notifications.is_timer());
+            fired |= payload.fired_timers;
+        }
+        assert_eq!(fired, 0b1010);
+    });
+}
+
+#[test]
+fn hosted_notify() {
+    let system = System::new();
+    let (done, wait_done) = mpsc::channel();
+    system
+        .spawn_task(2, move || {
+            let payload = NotificationPayload::parse(&ipc_recv(0).ok().unwrap()).unwrap();
+            done.send(payload.notifications.is_irq()).unwrap();
+        })
+        .ok()
+        .unwrap();
+    system.notify(2, Notifications::irq()).ok().unwrap();
+    assert!(wait_done.recv().unwrap());
+}
+
+#[test]
+fn hosted_abort() {
+    let system = System::new();
+    let pc = system.register_entry(|| {
+        // Task 4 never sends.
+        let aborted = matches!(ipc_recv(4), KResult::Aborted);
+        ipc_send(3, &mut message(ECHO, aborted as u8)).ok().unwrap();
+    });
+    system.run_as_task(3, || {
+        // We are the pager of the task 2.
+        create_task(2, pc, 0).ok().unwrap();
+        // Wait for the task 2 to be blocked.
+        while !matches!(ipc_abort(2), KResult::Ok(_)) {
+            std::thread::yield_now();
+        }
+        assert_eq!(ipc_recv(2).ok().unwrap().raw[0], 1);
+    });
+}
+
+#[test]
+fn hosted_abort_not_permitted() {
+    let system = System::new();
+    system.spawn_task(2, echo_server).ok().unwrap();
+    system.run_as_task(3, || {
+        // Neither the pager of the task 2 nor init.
+        assert!(matches!(ipc_abort(2), KResult::NotPermitted));
+    });
+}
+
+#[test]
+fn hosted_destroy_server() {
+    let system = System::new();
+    let pc = system.register_entry(echo_server);
+    system.run_as_task(1, || {
+        create_task(2, pc, 0).ok().unwrap();
+        assert_eq!(task_info(2).ok().unwrap().pager, 1);
+        assert_eq!(ipc_call(2, &mut message(ECHO, 1)).ok().unwrap().raw[0], 2);
+        destroy_task(2).ok().unwrap();
+        assert!(matches!(
+            ipc_call(2, &mut message(ECHO, 1)),
+            KResult::InvalidTask
+        ));
+
+        // The TID can be reused.
+        create_task(2, pc, 0).ok().unwrap();
+        assert_eq!(ipc_call(2, &mut message(ECHO, 3)).ok().unwrap().raw[0], 4);
+    });
+}
+
+#[test]
+fn hosted_destroy_not_permitted() {
+    let system = System::new();
+    system.spawn_task(2, echo_server).ok().unwrap();
+    system.run_as_task(3, || {
+        // Neither the creator of the task 2 nor init.
+        assert!(matches!(destroy_task(2), KResult::NotPermitted));
+        assert_eq!(task_info(2).ok().unwrap().dst_tid, NOT_SENDING);
+    });
+}
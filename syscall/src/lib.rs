@@ -1,4 +1,4 @@
-#![no_std]
+#![cfg_attr(not(any(test, feature = "hosted")), no_std)]
 #![feature(concat_bytes)]
 #![feature(maybe_uninit_slice)]
 #![feature(asm_const)]
@@ -11,3 +11,12 @@ mod arch;
 pub mod error;
 pub mod payload;
 pub mod syscall;
+
+#[cfg(all(
+    not(all(target_arch = "riscv32", feature = "cramp32")),
+    any(test, feature = "hosted")
+))]
+pub use arch::hosted::sim as hosted;
+
+#[cfg(test)]
+mod hosted_test;
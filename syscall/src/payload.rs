@@ -1,5 +1,7 @@
 use core::marker::PhantomData;
-use klib::ipc::{Message, MessageType};
+use core::mem;
+use core::ptr;
+use klib::ipc::{ExceptionPayload, Message, MessageType};
 
 pub struct MessageAdapter<Payload: PayloadForMessageType>(PhantomData<Payload>);
 
@@ -12,11 +14,23 @@ impl<Payload: PayloadForMessageType> MessageAdapter<Payload> {
         unsafe { &*(message.raw.as_ptr() as *const Payload) }
     }
 
+    // Copies only the payload: it may be smaller than `raw`, or even empty.
     pub fn message(payload: &Payload) -> Message {
-        Message {
+        let mut message = Message {
             message_type: Payload::MESSAGE_TYPE,
-            src_tid: 0,
-            raw: unsafe { *(payload as *const Payload as *const [u8; 24]) },
-        }
+            ..Message::zeroed()
+        };
+        unsafe {
+            ptr::copy_nonoverlapping(
+                payload as *const Payload as *const u8,
+                message.raw.as_mut_ptr(),
+                mem::size_of::<Payload>(),
+            )
+        };
+        message
     }
 }
+
+impl PayloadForMessageType for ExceptionPayload {
+    const MESSAGE_TYPE: MessageType = MessageType::EXCEPTION;
+}
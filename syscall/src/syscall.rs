@@ -1,6 +1,8 @@
 use crate::arch;
 use klib::ipc::Message;
 use klib::result::KResult;
+use klib::task::TaskInfo;
+use klib::time::Instant;
 
 pub fn nop() -> KResult<()> {
     arch::syscall::nop()
@@ -10,6 +12,31 @@ pub fn set_timer(timeout: u32) -> KResult<()> {
     arch::syscall::set_timer(timeout)
 }
 
+// Arms the timer `id` to fire after `ms` milliseconds, and then every `period`
+// milliseconds unless `period` is zero. Fired timers are reported in
+// `NotificationPayload::fired_timers`.
+pub fn timer_set(id: u32, ms: u32, period: u32) -> KResult<()> {
+    arch::syscall::timer_set(id, ms, period)
+}
+
+pub fn timer_cancel(id: u32) -> KResult<()> {
+    arch::syscall::timer_cancel(id)
+}
+
+// Arms the timer `id` to fire once at `deadline`.
+pub fn timer_set_until(id: u32, deadline: Instant) -> KResult<()> {
+    arch::syscall::timer_set_until(id, deadline)
+}
+
+// Sleep-until variant of `set_timer`: arms the timer 0 to fire at `deadline`.
+pub fn set_timer_until(deadline: Instant) -> KResult<()> {
+    arch::syscall::timer_set_until(0, deadline)
+}
+
+pub fn get_time() -> KResult<Instant> {
+    arch::syscall::get_time()
+}
+
 pub fn console_write(s: &[u8]) -> KResult<()> {
     arch::syscall::console_write(s)
 }
@@ -18,18 +45,100 @@ pub fn ipc_recv(src_tid: u32) -> KResult<Message> {
     arch::syscall::ipc_recv(src_tid)
 }
 
-pub fn ipc_send(dst_tid: u32, message: &Message) -> KResult<()> {
+pub fn ipc_recv_noblock(src_tid: u32) -> KResult<Message> {
+    arch::syscall::ipc_recv_noblock(src_tid)
+}
+
+// Zero `timeout` means no timeout, here and in the other `*_timeout` calls.
+pub fn ipc_recv_timeout(src_tid: u32, timeout: u32) -> KResult<Message> {
+    arch::syscall::ipc_recv_timeout(src_tid, timeout)
+}
+
+// Receives a message with an out-of-line payload of up to `buffer.len()`
+// bytes into `buffer`. `message.ool_len` is the length received.
+pub fn ipc_recv_ool(src_tid: u32, buffer: &mut [u8]) -> KResult<Message> {
+    arch::syscall::ipc_recv_ool(src_tid, buffer)
+}
+
+// On `KResult::TooLarge`, the out-of-line payload doesn't fit in the buffer of
+// the receiver: `message.ool_len` is set to its capacity so that the sender can
+// retry with a shorter payload. The same goes for the other sends and calls.
+pub fn ipc_send(dst_tid: u32, message: &mut Message) -> KResult<()> {
     arch::syscall::ipc_send(dst_tid, message)
 }
 
-pub fn ipc_call(dst_tid: u32, message: &Message) -> KResult<Message> {
+pub fn ipc_call(dst_tid: u32, message: &mut Message) -> KResult<Message> {
     arch::syscall::ipc_call(dst_tid, message)
 }
 
-pub fn ipc_send_noblock(dst_tid: u32, message: &Message) -> KResult<()> {
+pub fn ipc_send_noblock(dst_tid: u32, message: &mut Message) -> KResult<()> {
     arch::syscall::ipc_send_noblock(dst_tid, message)
 }
 
+pub fn ipc_send_timeout(dst_tid: u32, message: &mut Message, timeout: u32) -> KResult<()> {
+    arch::syscall::ipc_send_timeout(dst_tid, message, timeout)
+}
+
+pub fn ipc_call_timeout(dst_tid: u32, message: &mut Message, timeout: u32) -> KResult<Message> {
+    arch::syscall::ipc_call_timeout(dst_tid, message, timeout)
+}
+
+// Replies to `dst_tid` waiting in `ipc_call` to us. Unlike `ipc_send`, it never
+// blocks: it fails with `KResult::NotReady` if the task is not waiting for the
+// reply. The reply can't carry an out-of-line payload: it fails with
+// `KResult::NotAcceptable`.
+pub fn ipc_reply(dst_tid: u32, message: &Message) -> KResult<()> {
+    arch::syscall::ipc_reply(dst_tid, message)
+}
+
+// Makes the pending IPC operation of `tid` fail with `KResult::Aborted`. Only
+// the pager of the task and init may abort it.
+pub fn ipc_abort(tid: u32) -> KResult<()> {
+    arch::syscall::ipc_abort(tid)
+}
+
 pub fn create_task(tid: u32, pc: u32, sp: u32) -> KResult<()> {
     arch::syscall::create_task(tid, pc, sp)
 }
+
+// Creates a task running on `stack`. The kernel measures the peak usage of
+// the stack (`TaskInfo::stack_peak`), and its lowest 16 bytes must not be
+// used. They're a guard inaccessible to the task only when the kernel runs
+// tasks in U-mode (the umode feature): otherwise there is no guard and an
+// overflow silently corrupts the memory below the stack. The stack must be
+// aligned to 16 bytes.
+pub fn create_task_with_stack(tid: u32, pc: u32, stack: &'static mut [u8]) -> KResult<()> {
+    arch::syscall::create_task_with_stack(tid, pc, stack)
+}
+
+pub fn destroy_task(tid: u32) -> KResult<()> {
+    arch::syscall::destroy_task(tid)
+}
+
+pub fn exit_task() -> ! {
+    arch::syscall::exit_task()
+}
+
+pub fn task_self() -> KResult<u32> {
+    arch::syscall::task_self()
+}
+
+pub fn task_info(tid: u32) -> KResult<TaskInfo> {
+    arch::syscall::task_info(tid)
+}
+
+pub fn schedule_task(tid: u32, priority: u32, time_slice: u32) -> KResult<()> {
+    arch::syscall::schedule_task(tid, priority, time_slice)
+}
+
+pub fn irq_acquire(irq: u32) -> KResult<()> {
+    arch::syscall::irq_acquire(irq)
+}
+
+pub fn irq_release(irq: u32) -> KResult<()> {
+    arch::syscall::irq_release(irq)
+}
+
+pub fn irq_ack(irq: u32) -> KResult<()> {
+    arch::syscall::irq_ack(irq)
+}